@@ -1,70 +1,307 @@
 from PIL.Image import Image
 import PIL
 from .inpaint import (
+    InpaintError,
     telea_inpaint,
+    telea_inpaint_batch,
 )
 import numpy as np
-from typing import Union
-
-
-class InpaintError(Exception):
-    """Raised when inpainting failed for whatever reason."""
+import sys
+from typing import Any, Callable, Optional, Sequence, Union
 
 
 def telea(
     image: Union[Image, np.array],
     mask: Union[Image, np.array],
     radius: int = 5,
-) -> Union[Image, np.array]:
+    progress: Optional[Callable[[int, int], Optional[bool]]] = None,
+    inplace: bool = False,
+    layout: str = "HWC",
+) -> Union[Image, np.array, Any]:
     """Inpaint the image using the Telea algorithm
 
     Args:
-        image: image to inpaint
-        mask: image containing black and white mask for region to inpaint
+        image: image to inpaint, either a PIL image, a 2 dimensional
+               grayscale or 3 dimensional numpy array, or any object
+               supporting DLPack like a CPU torch tensor
+        mask: image containing black and white mask for region to inpaint,
+              of which the first channel is used when it has channels
         radius (optional): radius of near pixels that are considered for
                            inpainting. Defaults to 5.
+        progress (optional): called with the amount of inpainted pixels and
+                             the total amount of masked pixels. Return False
+                             to cancel inpainting.
+        inplace (optional): inpaint the writable numpy array itself instead of
+                            a copy. Only supported for arrays of a natively
+                            inpainted dtype. Defaults to False.
+        layout (optional): "HWC" when the channels are the last dimension, or
+                           "CHW" when they are the first, like torch tensors.
+                           Masks with channels use the same layout.
+                           Defaults to "HWC".
 
     Raises:
-        InpaintError: if something goes unrecoverably wrong.
+        InpaintError: if something goes unrecoverably wrong, when the image or
+                      mask is not supported or when inpainting has been
+                      cancelled.
+        ValueError: if inplace is requested for an image that can't be
+                    inpainted in place, or when the layout is unknown.
 
     Returns:
-        inpainted image
+        inpainted image, of the same type as the image
     """
 
     is_pil_image = isinstance(image, PIL.Image.Image)
+    if is_pil_image and image.mode in ("P", "PA"):
+        raise InpaintError(
+            f"Unsupported image mode {image.mode}, convert palette images to "
+            "RGB or RGBA first"
+        )
 
-    image_array = np.array(image) if is_pil_image else image
-    mask_array = np.array(mask)[:, :, 0] if is_pil_image else mask
+    _check_layout(layout)
 
-    original_image_type = image_array.dtype
-    image_array = _convert_to_float(image_array)
-    mask_array = _convert_to_float(mask_array)
+    source = np.array(image) if is_pil_image else _from_dlpack(image)
+    image_array = _native_byte_order(source)
+    mask = _from_dlpack(mask)
+    if layout == "CHW" and np.ndim(mask) == 3:
+        mask = np.asarray(mask)[0]
+    mask_array = _mask_array(mask)
 
-    try:
-        output: np.array = telea_inpaint(
-            image_array,
-            mask_array,
-            radius,
+    original_image_type = image_array.dtype
+    if inplace and (
+        is_pil_image
+        or image_array is not source
+        or original_image_type.type not in _NATIVE_TYPES
+    ):
+        raise ValueError(
+            "Inpainting in place is only supported for numpy arrays and DLPack "
+            "tensors in the byte order of the machine of dtype "
+            + ", ".join(np.dtype(type).name for type in _NATIVE_TYPES)
+        )
+    if image_array.ndim not in (2, 3):
+        raise InpaintError(
+            "Unsupported image, expected a 2 dimensional grayscale or 3 "
+            f"dimensional array, got shape {image_array.shape}"
+        )
+    if layout == "CHW" and image_array.ndim == 3:
+        image_array = np.moveaxis(image_array, 0, -1)
+    if image_array.shape[:2] != mask_array.shape:
+        raise InpaintError(
+            f"Dimensions of image {image_array.shape[:2]} and mask "
+            f"{mask_array.shape} don't match"
         )
-    except RuntimeError as error: 
-        raise InpaintError(str(error)) from error
 
-    if not np.issubdtype(original_image_type, np.floating):
-        output *= np.iinfo(original_image_type).max
+    is_grayscale = image_array.ndim == 2
+    if is_grayscale:
+        image_array = image_array[:, :, np.newaxis]
+    if original_image_type.type not in _NATIVE_TYPES:
+        image_array = _convert_to_float(image_array)
+
+    output: np.array = telea_inpaint(
+        image_array,
+        mask_array,
+        radius,
+        progress,
+        inplace,
+    )
+
+    if output.dtype != original_image_type:
+        output = _convert_from_float(output, original_image_type)
+    if inplace:
+        return image
+    if is_grayscale:
+        output = output[:, :, 0]
+    elif layout == "CHW":
+        output = np.moveaxis(output, -1, 0)
 
     if is_pil_image:
-        return PIL.Image.fromarray(output.astype(original_image_type))
+        return PIL.Image.fromarray(output)
+
+    return _like(output, image)
+
+
+def telea_batch(
+    images: Union[Sequence[np.array], np.array],
+    masks: Union[Sequence[np.array], np.array],
+    radius: int = 5,
+    layout: str = "HWC",
+) -> Union[np.array, Any]:
+    """Inpaint a stack of images using the Telea algorithm, in parallel
+
+    Args:
+        images: stack of images of shape (N, H, W, C), or (N, H, W) for
+                grayscale images, a list of equally sized images or any
+                object supporting DLPack like a CPU torch tensor
+        masks: stack of masks of shape (N, H, W), of which the first channel
+               is used when it has channels, or a single mask of shape (H, W)
+               for all images
+        radius (optional): radius of near pixels that are considered for
+                           inpainting. Defaults to 5.
+        layout (optional): "HWC" for stacks of shape (N, H, W, C), or "CHW"
+                           for stacks of shape (N, C, H, W) like torch
+                           tensors. Defaults to "HWC".
+
+    Raises:
+        InpaintError: if something goes unrecoverably wrong or when the images
+                      or masks are not supported.
+        ValueError: when the layout is unknown.
+
+    Returns:
+        stack of inpainted images, in the type, shape and dtype of the images
+    """
+
+    _check_layout(layout)
+    image_array = _native_byte_order(_from_dlpack(images))
+    if image_array.ndim not in (3, 4):
+        raise InpaintError(
+            "Unsupported images, expected a 3 dimensional grayscale or 4 "
+            f"dimensional stack, got shape {image_array.shape}"
+        )
+    mask_array = _native_byte_order(_from_dlpack(masks))
+    if layout == "CHW" and mask_array.ndim == 4:
+        mask_array = mask_array[:, 0]
+    if mask_array.ndim == 2:
+        mask_array = np.broadcast_to(mask_array, image_array.shape[:1] + mask_array.shape)
+    mask_array = _mask_array(mask_array, ndim=3)
+    if layout == "CHW" and image_array.ndim == 4:
+        image_array = np.moveaxis(image_array, 1, -1)
+    if image_array.shape[:3] != mask_array.shape:
+        raise InpaintError(
+            f"Dimensions of images {image_array.shape[:3]} and masks "
+            f"{mask_array.shape} don't match"
+        )
+
+    original_image_type = image_array.dtype
+    is_grayscale = image_array.ndim == 3
+    if is_grayscale:
+        image_array = image_array[..., np.newaxis]
+    if original_image_type.type not in _NATIVE_TYPES:
+        image_array = _convert_to_float(image_array)
+
+    output: np.array = telea_inpaint_batch(image_array, mask_array, radius)
+
+    if output.dtype != original_image_type:
+        output = _convert_from_float(output, original_image_type)
+    if is_grayscale:
+        output = output[..., 0]
+    elif layout == "CHW":
+        output = np.moveaxis(output, -1, 1)
+
+    return _like(output, images)
+
+
+# Types that are inpainted in their own dtype, without converting them to float
+_NATIVE_TYPES = (
+    np.uint8,
+    np.uint16,
+    np.uint32,
+    np.int32,
+    np.float16,
+    np.float32,
+    np.float64,
+)
+
+
+def _check_layout(layout: str):
+    """Make sure the layout of the channels is known
+
+    Args:
+        layout: "HWC" or "CHW"
+
+    Raises:
+        ValueError: if the layout is unknown
+    """
+
+    if layout not in ("HWC", "CHW"):
+        raise ValueError(f"Unknown layout {layout}, expected HWC or CHW")
+
+
+def _from_dlpack(value: Any):
+    """View objects supporting DLPack, like torch tensors, as numpy array without copying
+
+    Args:
+        value: object to view, returned as is when it doesn't support DLPack
+
+    Raises:
+        InpaintError: if the tensor can't be viewed, like tensors on the GPU
+    """
+
+    if isinstance(value, np.ndarray) or not hasattr(value, "__dlpack__"):
+        return value
+    try:
+        return np.from_dlpack(value)
+    except (BufferError, RuntimeError, TypeError) as error:
+        raise InpaintError(f"Unsupported DLPack tensor: {error}") from error
+
+
+def _like(array: np.array, original: Any):
+    """Return the array as the type of the original, through DLPack without copying
+
+    Args:
+        array: numpy array to return
+        original: object of which the type is returned, like a torch tensor. Its
+                  module needs a `from_dlpack` function, otherwise the numpy
+                  array is returned.
+    """
+
+    if isinstance(original, np.ndarray) or not hasattr(original, "__dlpack__"):
+        return array
+    module = sys.modules.get(type(original).__module__.split(".")[0])
+    from_dlpack = getattr(module, "from_dlpack", None)
+    if from_dlpack is None:
+        return array
+    return from_dlpack(array)
+
+
+def _native_byte_order(image_array: np.array):
+    """Convert the array to the byte order of the machine, like big endian 16 bit images
+
+    Args:
+        image_array: array to convert
+    """
+
+    image_array = np.asarray(image_array)
+    if image_array.dtype.isnative:
+        return image_array
+    return image_array.astype(image_array.dtype.newbyteorder("="))
+
+
+def _mask_array(mask: Union[Image, np.array], ndim: int = 2):
+    """Convert the mask into an array of a dtype supported by the bindings
 
-    return output
+    Args:
+        mask: PIL image or numpy array, of which the first channel is used
+        ndim (optional): dimensions of the mask without channels. Defaults to 2.
+    """
+
+    mask_array = _native_byte_order(mask)
+    if mask_array.ndim == ndim + 1:
+        mask_array = mask_array[..., 0]
+    if mask_array.ndim != ndim:
+        raise InpaintError(
+            f"Unsupported mask, expected a {ndim} dimensional or {ndim + 1} "
+            f"dimensional array, got shape {mask_array.shape}"
+        )
+    if mask_array.dtype.type not in _NATIVE_TYPES + (np.bool_,):
+        mask_array = _convert_to_float(mask_array)
+    return mask_array
 
 
 def _convert_to_float(image_array: np.array):
-    """Convert
+    """Convert integers to floats between 0 and 1, and other floats to float32
 
     Args:
         image_array: array to convert to float
+
+    Raises:
+        InpaintError: if the dtype is not numeric
     """
 
+    if image_array.dtype.type is np.bool_:
+        return image_array.astype(np.float32)
+    if not np.issubdtype(image_array.dtype, np.number) or np.issubdtype(
+        image_array.dtype, np.complexfloating
+    ):
+        raise InpaintError(f"Unsupported dtype {image_array.dtype}")
     if not np.issubdtype(image_array.dtype, np.floating):
         image_array = image_array.astype(np.float32, copy=False) / float(
             np.iinfo(
@@ -72,4 +309,27 @@ def _convert_to_float(image_array: np.array):
             ).max
         )
 
-    return image_array
+    return image_array.astype(np.float32, copy=False)
+
+
+def _convert_from_float(image_array: np.array, image_type: np.dtype):
+    """Convert the float array back into the original type
+
+    Args:
+        image_array: float array to convert
+        image_type: type to convert to, integers are scaled to their range
+    """
+
+    if np.issubdtype(image_type, np.floating):
+        return image_array.astype(image_type)
+    if image_type.type is np.bool_:
+        return image_array >= 0.5
+
+    limits = np.iinfo(image_type)
+    return np.clip(np.rint(image_array * limits.max), limits.min, limits.max).astype(
+        image_type
+    )
+
+
+# Imported last, as it is built on top of the functions above
+from . import cv2_compat  # noqa: E402
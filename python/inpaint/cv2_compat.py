@@ -0,0 +1,91 @@
+"""Drop-in replacement of `cv2.inpaint`, with the same arguments and dtype behaviour.
+
+Replace `import cv2` with `from inpaint import cv2_compat as cv2` in scripts that only
+use OpenCV for inpainting.
+"""
+
+from typing import Optional
+
+import numpy as np
+
+from . import InpaintError, telea
+
+INPAINT_NS = 0
+INPAINT_TELEA = 1
+
+# Combinations of dtype and amount of channels that cv2.inpaint supports
+_SRC_TYPES = (
+    (np.dtype(np.uint8), 1),
+    (np.dtype(np.uint8), 3),
+    (np.dtype(np.uint16), 1),
+    (np.dtype(np.float32), 1),
+)
+
+
+def inpaint(
+    src: np.array,
+    mask: np.array,
+    radius: float,
+    flags: int,
+    dst: Optional[np.array] = None,
+) -> np.array:
+    """Inpaint the image like `cv2.inpaint`
+
+    Args:
+        src: uint8 image with 1 or 3 channels, like a BGR image, or a uint16
+             or float32 image with 1 channel. The order of the channels
+             doesn't matter.
+        mask: uint8 mask of shape (H, W) or (H, W, 1), where non-zero pixels
+              are inpainted.
+        radius: radius of near pixels that are considered for inpainting,
+                rounded and clamped between 1 and 100 like OpenCV does.
+        flags: INPAINT_TELEA. INPAINT_NS is not available yet.
+        dst (optional): array to write the inpainted image into.
+
+    Raises:
+        InpaintError: if the image or mask is not supported by cv2.inpaint.
+        NotImplementedError: if INPAINT_NS is requested.
+        ValueError: if the flags are unknown.
+
+    Returns:
+        inpainted image in the shape and dtype of the source, or dst if provided
+    """
+
+    if flags == INPAINT_NS:
+        raise NotImplementedError("INPAINT_NS is not available, use INPAINT_TELEA")
+    if flags != INPAINT_TELEA:
+        raise ValueError(f"Unknown inpainting flags {flags}")
+
+    src_array = np.asarray(src)
+    channels = src_array.shape[2] if src_array.ndim == 3 else 1
+    if src_array.ndim not in (2, 3) or (src_array.dtype, channels) not in _SRC_TYPES:
+        raise InpaintError(
+            "Unsupported image, expected uint8 with 1 or 3 channels, or uint16 or "
+            f"float32 with 1 channel, got shape {src_array.shape} and dtype "
+            f"{src_array.dtype}"
+        )
+
+    mask_array = np.asarray(mask)
+    if mask_array.ndim == 3 and mask_array.shape[2] == 1:
+        mask_array = mask_array[:, :, 0]
+    if mask_array.ndim != 2 or mask_array.dtype != np.uint8:
+        raise InpaintError(
+            "Unsupported mask, expected uint8 with 1 channel, got shape "
+            f"{np.shape(mask)} and dtype {mask_array.dtype}"
+        )
+    if mask_array.shape != src_array.shape[:2]:
+        raise InpaintError(
+            f"Dimensions of image {src_array.shape[:2]} and mask "
+            f"{mask_array.shape} don't match"
+        )
+
+    output = telea(
+        src_array,
+        mask_array != 0,
+        min(max(int(np.rint(radius)), 1), 100),
+    )
+
+    if dst is None:
+        return output
+    np.copyto(dst, output)
+    return dst
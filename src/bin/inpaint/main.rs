@@ -0,0 +1,515 @@
+//! Command line interface to inpaint images without writing any code.
+//!
+//! ```bash
+//! inpaint --image in.png --mask mask.png --out out.png --algorithm telea --radius 5
+//! inpaint --image plate.####.exr --mask mask.png --out clean.####.exr --frames 1001-1100
+//! ```
+mod sequence;
+
+use clap::{Parser, ValueEnum};
+use image::{DynamicImage, ImageBuffer, Pixel, Primitive};
+use image_ndarray::prelude::*;
+#[cfg(feature = "exr")]
+use inpaint::ExrImage;
+use inpaint::{
+    Error, PyramidOptions, TeleaOptions, telea_inpaint_pyramid, telea_inpaint_with_options,
+};
+use ndarray::Array2;
+use num_traits::AsPrimitive;
+use rayon::prelude::*;
+use sequence::{FrameRange, frame_path, is_pattern};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+/// Exit code for invalid arguments, the same as clap uses.
+const EXIT_USAGE: u8 = 2;
+/// Exit code when the image or mask could not be read or the output could not be written.
+const EXIT_IO: u8 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+/// Algorithm used for inpainting.
+enum Algorithm {
+    /// Telea algorithm at full resolution.
+    Telea,
+    /// Telea algorithm on a multi-resolution pyramid, which works better for large holes.
+    Pyramid,
+}
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "inpaint",
+    version,
+    about = "Inpaint the masked regions of an image.",
+    after_help = "Exit codes:\n  \
+        0  Success\n  \
+        1  Inpainting failed\n  \
+        2  Invalid arguments\n  \
+        3  Reading the image or mask, or writing the output failed\n  \
+        4  Image and mask dimensions don't match\n  \
+        5  The mask covers the full image"
+)]
+struct Args {
+    /// Image to inpaint, or a frame padded pattern like `plate.####.exr` with `--frames`.
+    #[arg(long)]
+    image: PathBuf,
+    /// Mask that defines the region to inpaint, read as grayscale.
+    ///
+    /// With `--frames`, a mask without `#` is used for all frames.
+    #[arg(long)]
+    mask: PathBuf,
+    /// Path to write the inpainted image to, the format is derived from the extension.
+    #[arg(long)]
+    out: PathBuf,
+    /// Range of frames to process, like `1001-1100`, which replace the `#` of the paths.
+    #[arg(long)]
+    frames: Option<FrameRange>,
+    /// Amount of frames processed in parallel, all cores are used by default.
+    #[arg(long, default_value_t = 0)]
+    jobs: usize,
+    /// Skip frames of which the output already exists.
+    #[arg(long)]
+    skip_existing: bool,
+    /// Algorithm used for inpainting.
+    #[arg(long, value_enum, default_value_t = Algorithm::Telea)]
+    algorithm: Algorithm,
+    /// Radius of near pixels that are considered for inpainting.
+    #[arg(long, default_value_t = 5)]
+    radius: i32,
+    /// Amount of levels when using the pyramid algorithm.
+    #[arg(long, default_value_t = 3)]
+    levels: usize,
+    /// Mask values in the 0-1 range above the threshold are inpainted.
+    #[arg(long, default_value_t = 0.0)]
+    mask_threshold: f32,
+    /// Inpaint the pixels below the threshold instead.
+    #[arg(long)]
+    invert_mask: bool,
+    /// Channels of OpenEXR images to inpaint, like `R,G,B,depth.Z`, all channels by default.
+    ///
+    /// OpenEXR images are only read this way when the output is OpenEXR as well, so all
+    /// other channels, layers and attributes are written back as is.
+    #[cfg(feature = "exr")]
+    #[arg(long, value_delimiter = ',')]
+    channels: Vec<String>,
+}
+
+/// Exit code for the errors of the library.
+fn exit_code(error: &Error) -> u8 {
+    match error {
+        Error::DimensionMismatch => 4,
+        Error::NoKnownPixels => 5,
+        _ => 1,
+    }
+}
+
+/// Threshold the grayscale mask into a mask of inpainted (255) and known (0) pixels.
+fn threshold_mask(mask: &DynamicImage, threshold: f32, invert: bool) -> Array2<u8> {
+    let mask = mask.to_luma32f();
+    let shape = (mask.height() as usize, mask.width() as usize);
+    Array2::from_shape_fn(shape, |(y, x)| {
+        let value = mask.get_pixel(x as u32, y as u32).0[0];
+        if (value > threshold) != invert {
+            255
+        } else {
+            0
+        }
+    })
+}
+
+/// Inpaint an image buffer of any pixel type with the algorithm of the arguments.
+fn inpaint_buffer<P, S>(
+    image: &mut ImageBuffer<P, Vec<S>>,
+    mask: &Array2<u8>,
+    args: &Args,
+) -> Result<(), Error>
+where
+    P: Pixel<Subpixel = S>,
+    S: Primitive + AsPrimitive<f32>,
+    f32: AsPrimitive<S>,
+{
+    let mut array = image.as_ndarray_mut();
+    match args.algorithm {
+        Algorithm::Telea => {
+            telea_inpaint_with_options(&mut array, &mask.view(), &TeleaOptions::new(args.radius))
+        }
+        Algorithm::Pyramid => telea_inpaint_pyramid(
+            &mut array,
+            &mask.view(),
+            &PyramidOptions::new(args.levels, args.radius),
+        ),
+    }
+}
+
+/// Inpaint the image in its own pixel type, so the bit depth is preserved.
+fn inpaint_image(image: &mut DynamicImage, mask: &Array2<u8>, args: &Args) -> Result<(), Error> {
+    match image {
+        DynamicImage::ImageLuma8(buffer) => inpaint_buffer(buffer, mask, args),
+        DynamicImage::ImageLumaA8(buffer) => inpaint_buffer(buffer, mask, args),
+        DynamicImage::ImageRgb8(buffer) => inpaint_buffer(buffer, mask, args),
+        DynamicImage::ImageRgba8(buffer) => inpaint_buffer(buffer, mask, args),
+        DynamicImage::ImageLuma16(buffer) => inpaint_buffer(buffer, mask, args),
+        DynamicImage::ImageLumaA16(buffer) => inpaint_buffer(buffer, mask, args),
+        DynamicImage::ImageRgb16(buffer) => inpaint_buffer(buffer, mask, args),
+        DynamicImage::ImageRgba16(buffer) => inpaint_buffer(buffer, mask, args),
+        DynamicImage::ImageRgb32F(buffer) => inpaint_buffer(buffer, mask, args),
+        DynamicImage::ImageRgba32F(buffer) => inpaint_buffer(buffer, mask, args),
+        other => {
+            let mut buffer = other.to_rgba32f();
+            inpaint_buffer(&mut buffer, mask, args)?;
+            *other = DynamicImage::ImageRgba32F(buffer);
+            Ok(())
+        }
+    }
+}
+
+/// Result of a single frame of a sequence.
+#[derive(Debug)]
+enum FrameResult {
+    Done(Duration),
+    Skipped,
+    Failed(u8, String),
+}
+
+fn read_image(path: &Path) -> Result<DynamicImage, (u8, String)> {
+    image::open(path).map_err(|error| (EXIT_IO, format!("{}: {error}", path.display())))
+}
+
+fn read_mask(path: &Path, args: &Args) -> Result<Array2<u8>, (u8, String)> {
+    Ok(threshold_mask(
+        &read_image(path)?,
+        args.mask_threshold,
+        args.invert_mask,
+    ))
+}
+
+/// Whether the path has the extension of an OpenEXR image.
+#[cfg(feature = "exr")]
+fn is_exr(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("exr"))
+}
+
+/// Inpaint the channels of an OpenEXR image, the same way as the library does.
+#[cfg(feature = "exr")]
+fn inpaint_exr(image: &mut ExrImage, mask: &Array2<u8>, args: &Args) -> Result<(), Error> {
+    let names = if args.channels.is_empty() {
+        image.channel_names()
+    } else {
+        args.channels.clone()
+    };
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    match args.algorithm {
+        Algorithm::Telea => {
+            image.telea_inpaint(&names, &mask.view(), &TeleaOptions::new(args.radius))
+        }
+        Algorithm::Pyramid => image.telea_inpaint_pyramid(
+            &names,
+            &mask.view(),
+            &PyramidOptions::new(args.levels, args.radius),
+        ),
+    }
+}
+
+/// Inpaint an OpenEXR image with the mask and write it to the output.
+#[cfg(feature = "exr")]
+fn inpaint_exr_file(
+    image: &Path,
+    mask: &Array2<u8>,
+    out: &Path,
+    args: &Args,
+) -> Result<(), (u8, String)> {
+    let io_error = |path: &Path, error: Error| (EXIT_IO, format!("{}: {error}", path.display()));
+    let mut exr = ExrImage::open(image).map_err(|error| io_error(image, error))?;
+    inpaint_exr(&mut exr, mask, args).map_err(|error| (exit_code(&error), error.to_string()))?;
+    exr.save(out).map_err(|error| io_error(out, error))
+}
+
+/// Inpaint a single image with the mask and write it to the output.
+fn inpaint_file(
+    image: &Path,
+    mask: &Array2<u8>,
+    out: &Path,
+    args: &Args,
+) -> Result<(), (u8, String)> {
+    #[cfg(feature = "exr")]
+    if is_exr(image) && is_exr(out) {
+        return inpaint_exr_file(image, mask, out, args);
+    }
+    let mut image = read_image(image)?;
+    inpaint_image(&mut image, mask, args)
+        .map_err(|error| (exit_code(&error), error.to_string()))?;
+    image
+        .save(out)
+        .map_err(|error| (EXIT_IO, format!("{}: {error}", out.display())))
+}
+
+/// Inpaint every frame of the range in parallel, returning the result per frame.
+fn inpaint_sequence(
+    frames: FrameRange,
+    args: &Args,
+) -> Result<Vec<(u32, FrameResult)>, (u8, String)> {
+    if !is_pattern(&args.image) || !is_pattern(&args.out) {
+        return Err((
+            EXIT_USAGE,
+            "--image and --out need a frame pattern like `plate.####.exr` with --frames"
+                .to_string(),
+        ));
+    }
+    let static_mask = if is_pattern(&args.mask) {
+        None
+    } else {
+        Some(read_mask(&args.mask, args)?)
+    };
+
+    let inpaint_frame = |frame: u32| {
+        let out = frame_path(&args.out, frame);
+        if args.skip_existing && out.exists() {
+            return FrameResult::Skipped;
+        }
+        let start = Instant::now();
+        let result = match &static_mask {
+            Some(mask) => inpaint_file(&frame_path(&args.image, frame), mask, &out, args),
+            None => read_mask(&frame_path(&args.mask, frame), args)
+                .and_then(|mask| inpaint_file(&frame_path(&args.image, frame), &mask, &out, args)),
+        };
+        match result {
+            Ok(()) => FrameResult::Done(start.elapsed()),
+            Err((code, message)) => FrameResult::Failed(code, message),
+        }
+    };
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(args.jobs)
+        .build()
+        .map_err(|error| (1, error.to_string()))?;
+    Ok(pool.install(|| {
+        (frames.first..=frames.last)
+            .into_par_iter()
+            .map(|frame| (frame, inpaint_frame(frame)))
+            .collect()
+    }))
+}
+
+/// Print the result of every frame and the totals, returning the error of the first failed frame.
+fn summarize(results: &[(u32, FrameResult)], elapsed: Duration) -> Result<(), (u8, String)> {
+    let mut durations = Vec::new();
+    let mut failed = Vec::new();
+    for (frame, result) in results {
+        match result {
+            FrameResult::Done(duration) => {
+                println!("frame {frame}: {:.2}s", duration.as_secs_f32());
+                durations.push(*duration);
+            }
+            FrameResult::Skipped => println!("frame {frame}: skipped, output exists"),
+            FrameResult::Failed(code, message) => {
+                println!("frame {frame}: failed, {message}");
+                failed.push(*code);
+            }
+        }
+    }
+
+    let average = durations.iter().sum::<Duration>() / durations.len().max(1) as u32;
+    println!(
+        "{} inpainted, {} skipped, {} failed in {:.2}s ({:.2}s per frame)",
+        durations.len(),
+        results.len() - durations.len() - failed.len(),
+        failed.len(),
+        elapsed.as_secs_f32(),
+        average.as_secs_f32(),
+    );
+    match failed.first() {
+        Some(code) => Err((*code, format!("{} frame(s) failed", failed.len()))),
+        None => Ok(()),
+    }
+}
+
+fn run(args: &Args) -> Result<(), (u8, String)> {
+    match args.frames {
+        Some(frames) => {
+            let start = Instant::now();
+            let results = inpaint_sequence(frames, args)?;
+            summarize(&results, start.elapsed())
+        }
+        None => inpaint_file(&args.image, &read_mask(&args.mask, args)?, &args.out, args),
+    }
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err((code, message)) => {
+            eprintln!("error: {message}");
+            ExitCode::from(code)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma, RgbImage};
+
+    fn args(extra: &[&str]) -> Args {
+        let mut arguments = vec!["inpaint", "--image", "in.png", "--mask", "m.png"];
+        arguments.extend(["--out", "out.png"]);
+        arguments.extend(extra);
+        Args::parse_from(arguments)
+    }
+
+    #[test]
+    fn test_threshold_mask() {
+        let mask = DynamicImage::ImageLuma8(GrayImage::from_fn(4, 1, |x, _| {
+            Luma([[0, 64, 128, 255][x as usize]])
+        }));
+
+        assert_eq!(
+            threshold_mask(&mask, 0.0, false).row(0).to_vec(),
+            [0, 255, 255, 255]
+        );
+        assert_eq!(
+            threshold_mask(&mask, 0.5, false).row(0).to_vec(),
+            [0, 0, 255, 255]
+        );
+        assert_eq!(
+            threshold_mask(&mask, 0.5, true).row(0).to_vec(),
+            [255, 255, 0, 0]
+        );
+    }
+
+    #[test]
+    fn test_inpaint_image_keeps_pixel_type() {
+        let mut image = DynamicImage::ImageRgb8(RgbImage::from_fn(30, 10, |x, _| {
+            if x < 15 {
+                [10, 20, 30].into()
+            } else {
+                [200, 100, 50].into()
+            }
+        }));
+        let mut mask = Array2::zeros((10, 30));
+        mask[[5, 5]] = 255;
+        mask[[5, 25]] = 255;
+
+        for algorithm in ["telea", "pyramid"] {
+            let mut result = image.clone();
+            inpaint_image(&mut result, &mask, &args(&["--algorithm", algorithm])).unwrap();
+            assert_eq!(result, image);
+        }
+
+        image
+            .as_mut_rgb8()
+            .unwrap()
+            .put_pixel(5, 5, [0, 0, 0].into());
+        inpaint_image(&mut image, &mask, &args(&[])).unwrap();
+        assert_eq!(image.as_rgb8().unwrap().get_pixel(5, 5).0, [10, 20, 30]);
+    }
+
+    #[test]
+    fn test_inpaint_sequence() {
+        let directory = std::env::temp_dir().join(format!("inpaint-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        for frame in 1..=3 {
+            RgbImage::from_pixel(16, 16, [frame * 50, 0, 0].into())
+                .save(directory.join(format!("plate.{frame:04}.png")))
+                .unwrap();
+        }
+        let mut mask = GrayImage::new(16, 16);
+        mask.put_pixel(8, 8, Luma([255]));
+        mask.save(directory.join("mask.png")).unwrap();
+        RgbImage::new(16, 16)
+            .save(directory.join("clean.0003.png"))
+            .unwrap();
+
+        let path = |name: &str| directory.join(name).to_string_lossy().into_owned();
+        let sequence_args = Args::parse_from([
+            "inpaint",
+            "--image",
+            &path("plate.####.png"),
+            "--mask",
+            &path("mask.png"),
+            "--out",
+            &path("clean.####.png"),
+            "--frames",
+            "1-4",
+            "--skip-existing",
+        ]);
+        let results = inpaint_sequence(sequence_args.frames.unwrap(), &sequence_args).unwrap();
+        std::fs::remove_dir_all(&directory).ok();
+
+        assert_eq!(
+            results.iter().map(|(frame, _)| *frame).collect::<Vec<_>>(),
+            [1, 2, 3, 4]
+        );
+        assert!(matches!(results[0].1, FrameResult::Done(_)));
+        assert!(matches!(results[1].1, FrameResult::Done(_)));
+        assert!(matches!(results[2].1, FrameResult::Skipped));
+        assert!(matches!(results[3].1, FrameResult::Failed(EXIT_IO, _)));
+        assert!(matches!(
+            summarize(&results, Duration::ZERO),
+            Err((EXIT_IO, _))
+        ));
+
+        let args = args(&["--frames", "1-4"]);
+        assert!(matches!(
+            inpaint_sequence(args.frames.unwrap(), &args),
+            Err((EXIT_USAGE, _))
+        ));
+    }
+
+    #[cfg(feature = "exr")]
+    #[test]
+    fn test_inpaint_exr_keeps_channels() {
+        use exr::prelude::{
+            AnyChannel, AnyChannels, Encoding, FlatSamples, Image, Layer, LayerAttributes,
+            SmallVec, Vec2, WritableImage, f16,
+        };
+
+        let (pixels, hole) = (16 * 16, 8 * 16 + 8);
+        let mut red = vec![f16::from_f32(0.5); pixels];
+        let mut depth = vec![100.0; pixels];
+        (red[hole], depth[hole]) = (f16::ZERO, 0.0);
+        let layer = Layer::new(
+            Vec2(16, 16),
+            LayerAttributes::default(),
+            Encoding::FAST_LOSSLESS,
+            AnyChannels::sort(SmallVec::from_vec(vec![
+                AnyChannel::new("R", FlatSamples::F16(red)),
+                AnyChannel::new("Z", FlatSamples::F32(depth)),
+            ])),
+        );
+        let directory = std::env::temp_dir().join(format!("inpaint-exr-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let (image, out) = (directory.join("plate.exr"), directory.join("clean.exr"));
+        Image::from_layer(layer).write().to_file(&image).unwrap();
+        let mut mask = Array2::zeros((16, 16));
+        mask[[8, 8]] = 255;
+
+        inpaint_file(&image, &mask, &out, &args(&["--channels", "Z"])).unwrap();
+        let result = ExrImage::open(&out).unwrap();
+        std::fs::remove_dir_all(&directory).ok();
+
+        assert_eq!(result.channel_names(), ["R", "Z"]);
+        assert!(matches!(
+            result.as_exr().layer_data[0].channel_data.list[0].sample_data,
+            FlatSamples::F16(_)
+        ));
+        let channels = result.channels(&["R", "Z"]).unwrap();
+        assert_eq!(channels[[8, 8, 0]], 0.0);
+        assert!((channels[[8, 8, 1]] - 100.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        let mut image = DynamicImage::ImageRgb8(RgbImage::new(4, 4));
+
+        let error = inpaint_image(&mut image, &Array2::zeros((3, 3)), &args(&[])).unwrap_err();
+        assert_eq!(exit_code(&error), 4);
+
+        let error =
+            inpaint_image(&mut image, &Array2::from_elem((4, 4), 255), &args(&[])).unwrap_err();
+        assert_eq!(exit_code(&error), 5);
+
+        assert!(Args::try_parse_from(["inpaint", "--image", "in.png"]).is_err());
+    }
+}
@@ -0,0 +1,90 @@
+//! Frame ranges and frame padded patterns of image sequences.
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Inclusive range of frames, like `1001-1100`.
+pub struct FrameRange {
+    /// First frame of the range.
+    pub first: u32,
+    /// Last frame of the range, which is processed as well.
+    pub last: u32,
+}
+
+impl FromStr for FrameRange {
+    type Err = String;
+
+    /// Parse a range like `1001-1100`, or a single frame like `1001`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let parse = |frame: &str| {
+            frame
+                .trim()
+                .parse::<u32>()
+                .map_err(|error| format!("invalid frame '{frame}': {error}"))
+        };
+        let (first, last) = match value.split_once('-') {
+            Some((first, last)) => (parse(first)?, parse(last)?),
+            None => (parse(value)?, parse(value)?),
+        };
+        if last < first {
+            return Err(format!("last frame {last} is before first frame {first}"));
+        }
+        Ok(Self { first, last })
+    }
+}
+
+/// Whether the path contains a `#` for the frame number.
+pub fn is_pattern(path: &Path) -> bool {
+    path.to_string_lossy().contains('#')
+}
+
+/// Path of a single frame, replacing the last run of `#` with the frame number.
+///
+/// The frame number is padded with zeros to the length of the run,
+/// so `plate.####.exr` becomes `plate.1001.exr` and `plate.##.exr` becomes `plate.05.exr`.
+pub fn frame_path(pattern: &Path, frame: u32) -> PathBuf {
+    let pattern = pattern.to_string_lossy();
+    let end = match pattern.rfind('#') {
+        Some(end) => end,
+        None => return PathBuf::from(pattern.as_ref()),
+    };
+    let start = pattern[..end].trim_end_matches('#').len();
+    let width = end + 1 - start;
+    PathBuf::from(format!(
+        "{}{frame:0width$}{}",
+        &pattern[..start],
+        &pattern[end + 1..]
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_range() {
+        assert_eq!(
+            "1001-1100".parse(),
+            Ok(FrameRange {
+                first: 1001,
+                last: 1100
+            })
+        );
+        assert_eq!("5".parse(), Ok(FrameRange { first: 5, last: 5 }));
+        assert!("1100-1001".parse::<FrameRange>().is_err());
+        assert!("a-b".parse::<FrameRange>().is_err());
+    }
+
+    #[test]
+    fn test_frame_path() {
+        let path = |pattern: &str, frame| frame_path(Path::new(pattern), frame);
+        assert_eq!(path("plate.####.exr", 1001), Path::new("plate.1001.exr"));
+        assert_eq!(path("plate.####.exr", 7), Path::new("plate.0007.exr"));
+        assert_eq!(path("plate.##.exr", 12345), Path::new("plate.12345.exr"));
+        assert_eq!(
+            path("shot#1/plate.#.png", 3),
+            Path::new("shot#1/plate.3.png")
+        );
+        assert_eq!(path("plate.png", 3), Path::new("plate.png"));
+    }
+}
@@ -0,0 +1,140 @@
+/// Conversion of inpainted values into the pixel type of an output image.
+use core::any::TypeId;
+use num_traits::AsPrimitive;
+
+/// Range of values of a pixel type, integers use their full range and floats 0 to 1.
+pub(crate) fn value_range<P: 'static>() -> (f32, f32) {
+    match TypeId::of::<P>() {
+        id if id == TypeId::of::<u8>() => (0.0, u8::MAX as f32),
+        id if id == TypeId::of::<u16>() => (0.0, u16::MAX as f32),
+        id if id == TypeId::of::<u32>() => (0.0, u32::MAX as f32),
+        id if id == TypeId::of::<u64>() => (0.0, u64::MAX as f32),
+        id if id == TypeId::of::<u128>() => (0.0, u128::MAX as f32),
+        id if id == TypeId::of::<i8>() => (i8::MIN as f32, i8::MAX as f32),
+        id if id == TypeId::of::<i16>() => (i16::MIN as f32, i16::MAX as f32),
+        id if id == TypeId::of::<i32>() => (i32::MIN as f32, i32::MAX as f32),
+        id if id == TypeId::of::<i64>() => (i64::MIN as f32, i64::MAX as f32),
+        id if id == TypeId::of::<i128>() => (i128::MIN as f32, i128::MAX as f32),
+        _ => (0.0, 1.0),
+    }
+}
+
+/// Whether the pixel type is an integer, which is the case for every type with another range
+fn is_integer<P: 'static>() -> bool {
+    value_range::<P>() != (0.0, 1.0)
+}
+
+#[cfg(not(feature = "libm"))]
+fn round(value: f32) -> f32 {
+    value.round()
+}
+
+#[cfg(feature = "libm")]
+fn round(value: f32) -> f32 {
+    libm::roundf(value)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Conversion of the values of the input image into the output image.
+///
+/// Values are first scaled, then clamped and finally rounded, before they are cast
+/// to the output type. The default conversion casts the values as is.
+///
+/// NaN values, which only occur when the input image contains NaN pixels themselves or near
+/// the mask, are kept as NaN for unclamped outputs and written as 0 (or the nearest bound
+/// of the clamp range) when the output is clamped, so they never wrap around.
+pub struct OutputConversion {
+    /// Factor the values are multiplied with.
+    pub scale: f32,
+    /// Lower and upper bound the scaled values are clamped to.
+    pub clamp: Option<(f32, f32)>,
+    /// Round to the nearest value, instead of truncating towards zero.
+    pub round: bool,
+}
+
+impl Default for OutputConversion {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            clamp: None,
+            round: false,
+        }
+    }
+}
+
+impl OutputConversion {
+    /// Conversion between the value ranges of two pixel types.
+    ///
+    /// Integers use their full range and floats the range from 0 to 1, so a `u8` input
+    /// results in `f32` values from 0 to 1 and an `f32` input in `u16` values from 0 to 65535.
+    /// Integer outputs are clamped to their range and rounded.
+    pub fn between<InputType: 'static, OutputType: 'static>() -> Self {
+        let (_, input_max) = value_range::<InputType>();
+        let (output_min, output_max) = value_range::<OutputType>();
+        let integer = is_integer::<OutputType>();
+        Self {
+            scale: output_max / input_max,
+            clamp: integer.then_some((output_min, output_max)),
+            round: integer,
+        }
+    }
+
+    /// Convert a single value to the output type.
+    pub fn convert<OutputType>(&self, value: f32) -> OutputType
+    where
+        OutputType: Copy + 'static,
+        f32: AsPrimitive<OutputType>,
+    {
+        let mut value = value * self.scale;
+        if let Some((min, max)) = self.clamp {
+            if value.is_nan() {
+                value = 0.0;
+            }
+            value = value.max(min).min(max);
+        }
+        if self.round {
+            value = round(value);
+        }
+        value.as_()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversion_integer_to_float() {
+        let conversion = OutputConversion::between::<u8, f32>();
+        assert_eq!(conversion.convert::<f32>(255.0), 1.0);
+        assert_eq!(conversion.convert::<f32>(0.0), 0.0);
+        assert_eq!(conversion.convert::<f32>(300.0), 300.0 / 255.0);
+    }
+
+    #[test]
+    fn test_conversion_float_to_integer() {
+        let conversion = OutputConversion::between::<f32, u8>();
+        assert_eq!(conversion.convert::<u8>(1.0), 255);
+        assert_eq!(conversion.convert::<u8>(0.5), 128);
+        assert_eq!(conversion.convert::<u8>(-0.5), 0);
+        assert_eq!(conversion.convert::<u8>(2.0), 255);
+
+        let conversion = OutputConversion::between::<u16, u16>();
+        assert_eq!(conversion.convert::<u16>(1000.6), 1001);
+        assert_eq!(conversion.convert::<u16>(70000.0), u16::MAX);
+
+        let conversion = OutputConversion::between::<i8, i8>();
+        assert_eq!(conversion.convert::<i8>(f32::NAN), 0);
+        assert_eq!(conversion.convert::<i8>(-200.0), i8::MIN);
+        assert_eq!(conversion.convert::<u8>(f32::NAN), 0);
+        assert!(
+            OutputConversion::between::<f32, f32>()
+                .convert::<f32>(f32::NAN)
+                .is_nan()
+        );
+
+        let conversion = OutputConversion::default();
+        assert_eq!(conversion.convert::<u8>(1000.6), 255);
+        assert_eq!(conversion.convert::<i16>(-3.7), -3);
+    }
+}
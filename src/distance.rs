@@ -0,0 +1,176 @@
+/// Exact Euclidean distance transform.
+///
+/// Implementation of the separable algorithm described by Felzenszwalb and Huttenlocher in
+/// https://cs.brown.edu/people/pfelzens/papers/dt-final.pdf
+///
+/// The transform is computed as a lower envelope of parabolas, first for every column and
+/// afterwards for every row, which gives the exact distance in linear time.
+use glam::USizeVec2;
+use ndarray::Array2;
+#[cfg(not(feature = "libm"))]
+use num_traits::Float;
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+
+/// Value used for pixels that have no seed, as used in the reference implementation of the paper
+const INFINITY: f64 = 1.0e20;
+
+#[cfg(not(feature = "libm"))]
+fn sqrt(value: f64) -> f64 {
+    value.sqrt()
+}
+
+#[cfg(feature = "libm")]
+fn sqrt(value: f64) -> f64 {
+    libm::sqrt(value)
+}
+
+/// Compute the squared distance transform of a one dimensional sampled function
+///
+/// The `vertices` and `boundaries` are scratch buffers, which need to have a length
+/// of at least `input.len()` and `input.len() + 1`.
+fn transform_1d(input: &[f64], output: &mut [f64], vertices: &mut [usize], boundaries: &mut [f64]) {
+    let length = input.len();
+    if length == 0 {
+        return;
+    }
+
+    let mut k = 0;
+    vertices[0] = 0;
+    boundaries[0] = f64::NEG_INFINITY;
+    boundaries[1] = f64::INFINITY;
+
+    for q in 1..length {
+        let q_float = q as f64;
+        let mut intersection;
+        loop {
+            let vertex = vertices[k];
+            let vertex_float = vertex as f64;
+            intersection = ((input[q] + q_float * q_float)
+                - (input[vertex] + vertex_float * vertex_float))
+                / (2.0 * q_float - 2.0 * vertex_float);
+            if intersection <= boundaries[k] {
+                k -= 1;
+                continue;
+            }
+            break;
+        }
+        k += 1;
+        vertices[k] = q;
+        boundaries[k] = intersection;
+        boundaries[k + 1] = f64::INFINITY;
+    }
+
+    k = 0;
+    for (q, value) in output.iter_mut().enumerate().take(length) {
+        while boundaries[k + 1] < q as f64 {
+            k += 1;
+        }
+        let offset = q as f64 - vertices[k] as f64;
+        *value = offset * offset + input[vertices[k]];
+    }
+}
+
+/// Compute the Euclidean distance of every pixel to the nearest seed pixel.
+///
+/// Pixels for which `is_seed` returns true get a distance of 0.
+/// When there are no seeds at all, every pixel will be set to `f32::MAX`.
+pub(crate) fn euclidean_distance_transform<F>(resolution: USizeVec2, is_seed: F) -> Array2<f32>
+where
+    F: Fn(usize, usize) -> bool,
+{
+    let mut squared = Array2::<f64>::from_shape_fn((resolution.y, resolution.x), |(y, x)| {
+        if is_seed(y, x) { 0.0 } else { INFINITY }
+    });
+
+    let longest = resolution.x.max(resolution.y);
+    let mut input = vec![0.0; longest];
+    let mut output = vec![0.0; longest];
+    let mut vertices = vec![0; longest];
+    let mut boundaries = vec![0.0; longest + 1];
+
+    for mut column in squared.columns_mut() {
+        let length = column.len();
+        for (target, value) in input.iter_mut().zip(column.iter()) {
+            *target = *value;
+        }
+        transform_1d(
+            &input[..length],
+            &mut output[..length],
+            &mut vertices,
+            &mut boundaries,
+        );
+        for (target, value) in column.iter_mut().zip(output.iter()) {
+            *target = *value;
+        }
+    }
+
+    for mut row in squared.rows_mut() {
+        let length = row.len();
+        for (target, value) in input.iter_mut().zip(row.iter()) {
+            *target = *value;
+        }
+        transform_1d(
+            &input[..length],
+            &mut output[..length],
+            &mut vertices,
+            &mut boundaries,
+        );
+        for (target, value) in row.iter_mut().zip(output.iter()) {
+            *target = *value;
+        }
+    }
+
+    squared.mapv(|value| {
+        if value >= INFINITY {
+            f32::MAX
+        } else {
+            sqrt(value) as f32
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distance_single_seed() {
+        let resolution = USizeVec2::new(7, 5);
+        let distances = euclidean_distance_transform(resolution, |y, x| y == 2 && x == 3);
+
+        for ((y, x), distance) in distances.indexed_iter() {
+            let expected = ((y as f32 - 2.0).powi(2) + (x as f32 - 3.0).powi(2)).sqrt();
+            assert_eq!(*distance, expected);
+        }
+    }
+
+    #[test]
+    fn test_distance_multiple_seeds() {
+        let resolution = USizeVec2::new(9, 9);
+        let seeds = [(0, 0), (8, 8), (4, 6)];
+        let distances = euclidean_distance_transform(resolution, |y, x| seeds.contains(&(y, x)));
+
+        for ((y, x), distance) in distances.indexed_iter() {
+            let expected = seeds
+                .iter()
+                .map(|(seed_y, seed_x)| {
+                    ((y as f32 - *seed_y as f32).powi(2) + (x as f32 - *seed_x as f32).powi(2))
+                        .sqrt()
+                })
+                .fold(f32::MAX, f32::min);
+            assert_eq!(*distance, expected);
+        }
+    }
+
+    #[test]
+    fn test_distance_without_seeds() {
+        let resolution = USizeVec2::new(4, 3);
+        let distances = euclidean_distance_transform(resolution, |_, _| false);
+
+        assert!(distances.iter().all(|distance| *distance == f32::MAX));
+    }
+}
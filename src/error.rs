@@ -15,14 +15,32 @@ pub enum Error {
     DimensionMismatch,
     #[error("Heap pop failed as it does not contain data.")]
     HeapDoesNotContainData,
+    #[error("Inpainting has been cancelled.")]
+    Cancelled,
+    #[error("The mask covers the full image, so there are no known pixels to inpaint from.")]
+    NoKnownPixels,
+    #[cfg(feature = "exr")]
+    #[error("Channel {0} does not exist in the image.")]
+    ChannelNotFound(String),
+    #[cfg(feature = "exr")]
+    #[error("OpenEXR error: {0}")]
+    Exr(#[from] ::exr::error::Error),
     #[error("NDArray had an error during initializaiton of shape: {0}")]
     NDArray(#[from] ndarray::ShapeError),
 }
 
+#[cfg(feature = "python-bindings")]
+pyo3::create_exception!(
+    inpaint,
+    InpaintError,
+    PyRuntimeError,
+    "Raised when inpainting failed for whatever reason."
+);
+
 #[cfg(feature = "python-bindings")]
 impl std::convert::From<Error> for PyErr {
     fn from(err: Error) -> PyErr {
-        PyRuntimeError::new_err(err.to_string())
+        InpaintError::new_err(err.to_string())
     }
 }
 
@@ -2,48 +2,239 @@
 #![allow(unused_imports)]
 #![cfg_attr(not(feature = "std"), no_std)]
 
+mod conversion;
+mod distance;
 mod error;
 pub use error::Error;
+#[cfg(feature = "exr")]
+mod openexr;
+#[cfg(feature = "exr")]
+pub use openexr::ExrImage;
 pub mod prelude;
+mod pyramid;
+mod queue;
+mod region;
 mod telea;
+mod tiled;
+pub use conversion::OutputConversion;
+#[cfg(feature = "half")]
+pub use half::{bf16, f16};
 pub use prelude::*;
-pub use telea::telea_inpaint;
+pub use pyramid::{PyramidOptions, telea_inpaint_pyramid};
+pub use telea::{
+    Connectivity, DistanceMethod, EikonalOrder, NarrowBandQueue, TeleaOptions, TeleaWorkspace,
+    telea_inpaint, telea_inpaint_into, telea_inpaint_with_options, telea_inpaint_with_progress,
+    telea_inpainted,
+};
+pub use tiled::{TileRegion, telea_inpaint_tiled};
 
 #[cfg(feature = "python-bindings")]
 #[pyo3::pymodule]
 mod inpaint {
-    use crate::error::Result;
+    use crate::error::InpaintError;
+    use crate::telea::{TeleaOptions, is_masked};
+    use core::ops::ControlFlow;
+    use ndarray::{Array, Array3, ArrayViewMut3, Axis, Dimension};
     use numpy::IntoPyArray;
-    use numpy::{PyArray3, PyReadonlyArray2, PyReadonlyArray3};
+    use numpy::{PyReadonlyArray, PyReadonlyArray3, PyReadonlyArray4, PyReadwriteArray3};
     use pyo3::Python;
     use pyo3::prelude::*;
 
-    fn telea_inpaint_inner_py<'py, T>(
+    #[pymodule_init]
+    fn init(module: &Bound<'_, PyModule>) -> PyResult<()> {
+        module.add("InpaintError", module.py().get_type::<InpaintError>())
+    }
+
+    /// Shape and dtype of a numpy array, or the type of any other object, for error messages.
+    fn describe_array(value: &Bound<'_, PyAny>) -> String {
+        match (value.getattr("shape"), value.getattr("dtype")) {
+            (Ok(shape), Ok(dtype)) => format!("an array of shape {shape} and dtype {dtype}"),
+            _ => match value.get_type().name() {
+                Ok(name) => format!("an object of type {name}"),
+                Err(_) => "an unknown object".to_string(),
+            },
+        }
+    }
+
+    /// Read the mask, or stack of masks, of any supported dtype into flags of the pixels to inpaint.
+    fn mask_flags<D: Dimension>(mask: &Bound<'_, PyAny>) -> PyResult<Array<u8, D>> {
+        macro_rules! mask_dtypes {
+            ($($dtype:ty),*) => {
+                $(if let Ok(mask) = mask.extract::<PyReadonlyArray<'_, $dtype, D>>() {
+                    return Ok(mask.as_array().mapv(|value| is_masked(value) as u8));
+                })*
+            };
+        }
+        mask_dtypes!(u8, u16, u32, i32, f32, f64);
+        #[cfg(feature = "half")]
+        mask_dtypes!(half::f16);
+        if let Ok(mask) = mask.extract::<PyReadonlyArray<'_, bool, D>>() {
+            return Ok(mask.as_array().mapv(u8::from));
+        }
+        Err(InpaintError::new_err(format!(
+            "Unsupported mask, expected a {} dimensional array of a numeric or bool dtype, got {}.",
+            D::NDIM.unwrap_or_default(),
+            describe_array(mask)
+        )))
+    }
+
+    /// Inpaint the view without holding the GIL, so other Python threads can run meanwhile.
+    ///
+    /// The GIL is only acquired again to call the progress callback.
+    fn telea_inpaint_view_py<T>(
+        py: Python<'_>,
+        mut image: ArrayViewMut3<'_, T>,
+        mask: &ndarray::Array2<u8>,
+        radius: i32,
+        progress: Option<Py<PyAny>>,
+    ) -> PyResult<()>
+    where
+        T: Clone + Copy + Send + num_traits::AsPrimitive<f32> + 'static,
+        f32: num_traits::AsPrimitive<T> + Clone + Copy,
+    {
+        py.detach(|| {
+            // Exceptions raised by the progress callback cancel inpainting and are raised again
+            let mut progress_error = None;
+            let result = crate::telea::telea_inpaint_with_progress(
+                &mut image,
+                &mask.view(),
+                &TeleaOptions::new(radius),
+                |filled, total| match progress.as_ref().map(|progress| {
+                    Python::attach(|py| {
+                        progress
+                            .call1(py, (filled, total))
+                            .map(|value| matches!(value.extract::<bool>(py), Ok(false)))
+                    })
+                }) {
+                    Some(Ok(true)) => ControlFlow::Break(()),
+                    Some(Err(error)) => {
+                        progress_error = Some(error);
+                        ControlFlow::Break(())
+                    }
+                    _ => ControlFlow::Continue(()),
+                },
+            );
+            if let Some(error) = progress_error {
+                return Err(error);
+            }
+            Ok(result?)
+        })
+    }
+
+    /// Inpaint the image natively in its own dtype.
+    ///
+    /// Supports `uint8`, `uint16`, `uint32`, `int32`, `float32` and `float64` images,
+    /// and `float16` when the `half` feature is enabled. Integer images are rounded.
+    /// The mask can have any of these dtypes or be a `bool` array, independent of the image.
+    ///
+    /// The GIL is released while inpainting. With `inplace` the writable image is
+    /// inpainted without copying it and returned, otherwise a new array is returned.
+    #[pyfunction]
+    #[pyo3(
+        name = "telea_inpaint",
+        signature = (image, mask, radius, progress = None, inplace = false)
+    )]
+    fn telea_inpaint_py<'py>(
+        py: Python<'py>,
+        image: Bound<'py, PyAny>,
+        mask: Bound<'py, PyAny>,
+        radius: i32,
+        progress: Option<Bound<'py, PyAny>>,
+        inplace: bool,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let mask = mask_flags(&mask)?;
+        let progress = progress.map(Bound::unbind);
+        macro_rules! image_dtypes {
+            ($($dtype:ty),*) => {
+                $(if inplace && image.extract::<PyReadonlyArray3<'py, $dtype>>().is_ok() {
+                    let mut array = image.extract::<PyReadwriteArray3<'py, $dtype>>()?;
+                    telea_inpaint_view_py(py, array.as_array_mut(), &mask, radius, progress)?;
+                    return Ok(image);
+                } else if let Ok(array) = image.extract::<PyReadonlyArray3<'py, $dtype>>() {
+                    let mut output = array.as_array().to_owned();
+                    telea_inpaint_view_py(py, output.view_mut(), &mask, radius, progress)?;
+                    return Ok(output.into_pyarray(py).into_any());
+                })*
+            };
+        }
+        image_dtypes!(u8, u16, u32, i32, f32, f64);
+        #[cfg(feature = "half")]
+        image_dtypes!(half::f16);
+        Err(InpaintError::new_err(format!(
+            "Unsupported image, expected a 3 dimensional array of dtype uint8, uint16, uint32, \
+             int32, float16, float32 or float64, got {}.",
+            describe_array(&image)
+        )))
+    }
+
+    /// Inpaint every image of the stack with the mask of the same index, without the GIL.
+    ///
+    /// The images are inpainted in parallel when the `rayon` feature is enabled.
+    fn telea_inpaint_batch_inner_py<'py, T>(
         py: Python<'py>,
-        image: PyReadonlyArray3<'py, T>,
-        mask: PyReadonlyArray2<'py, T>,
+        images: PyReadonlyArray4<'py, T>,
+        masks: &Array3<u8>,
         radius: i32,
-    ) -> Result<Bound<'py, PyArray3<T>>>
+    ) -> PyResult<Bound<'py, PyAny>>
     where
-        T: numpy::Element + Clone + Copy + num_traits::AsPrimitive<f32> + 'static,
+        T: numpy::Element + Clone + Copy + Send + num_traits::AsPrimitive<f32> + 'static,
         f32: num_traits::AsPrimitive<T> + Clone + Copy,
     {
-        let mut original_image = image.as_array().to_owned();
-        let mask_image = mask.as_array().to_owned();
+        let mut output = images.as_array().to_owned();
+        let (count, height, width, _) = output.dim();
+        if masks.dim() != (count, height, width) {
+            return Err(crate::Error::DimensionMismatch.into());
+        }
 
-        crate::telea::telea_inpaint(&mut original_image.view_mut(), &mask_image.view(), radius)?;
+        py.detach(|| {
+            let options = TeleaOptions::new(radius);
+            let pairs = output.outer_iter_mut().zip(masks.outer_iter());
+            let inpaint = |(mut image, mask): (ArrayViewMut3<'_, T>, _)| {
+                crate::telea::telea_inpaint_with_options(&mut image, &mask, &options)
+            };
 
-        Ok(original_image.into_pyarray(py))
+            #[cfg(feature = "rayon")]
+            {
+                use rayon::prelude::*;
+                pairs
+                    .collect::<Vec<_>>()
+                    .into_par_iter()
+                    .try_for_each(inpaint)
+            }
+            #[cfg(not(feature = "rayon"))]
+            pairs.into_iter().try_for_each(inpaint)
+        })?;
+
+        Ok(output.into_pyarray(py).into_any())
     }
 
+    /// Inpaint a stack of images of shape `(N, H, W, C)` with a stack of masks of `(N, H, W)`.
+    ///
+    /// Supports the same dtypes as `telea_inpaint`, and returns a new stack in the dtype of
+    /// the images. The GIL is released while inpainting.
     #[pyfunction]
-    #[pyo3(name = "telea_inpaint")]
-    fn telea_inpaint_py<'py>(
+    #[pyo3(name = "telea_inpaint_batch")]
+    fn telea_inpaint_batch_py<'py>(
         py: Python<'py>,
-        image: PyReadonlyArray3<'py, f32>,
-        mask: PyReadonlyArray2<'py, f32>,
+        images: Bound<'py, PyAny>,
+        masks: Bound<'py, PyAny>,
         radius: i32,
-    ) -> Result<Bound<'py, PyArray3<f32>>> {
-        telea_inpaint_inner_py::<f32>(py, image, mask, radius)
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let masks = mask_flags(&masks)?;
+        macro_rules! image_dtypes {
+            ($($dtype:ty),*) => {
+                $(if let Ok(images) = images.extract::<PyReadonlyArray4<'py, $dtype>>() {
+                    return telea_inpaint_batch_inner_py(py, images, &masks, radius);
+                })*
+            };
+        }
+        image_dtypes!(u8, u16, u32, i32, f32, f64);
+        #[cfg(feature = "half")]
+        image_dtypes!(half::f16);
+        Err(InpaintError::new_err(format!(
+            "Unsupported images, expected a 4 dimensional array of dtype uint8, uint16, uint32, \
+             int32, float16, float32 or float64, got {}.",
+            describe_array(&images)
+        )))
     }
 }
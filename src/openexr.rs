@@ -0,0 +1,458 @@
+/// OpenEXR images with named channels.
+///
+/// The channels of all layers are addressed by their full name, which is the name of the
+/// layer followed by the name of the channel, like `depth.Z` or `N.x`. Only the selected
+/// channels are changed, all other channels, layers and attributes are written back as is.
+use crate::conversion::OutputConversion;
+use crate::error::{Error, Result};
+use crate::pyramid::{PyramidOptions, telea_inpaint_pyramid};
+use crate::telea::{TeleaOptions, is_masked, telea_inpaint_with_options};
+use ::exr::image::{AnyChannel, AnyChannels, FlatImage, FlatSamples, Layer};
+use ::exr::meta::{BlockDescription, MetaData, attribute::LevelMode};
+use ::exr::prelude::{WritableImage, f16, read_all_flat_layers_from_file};
+use ndarray::{Array3, ArrayView2, ArrayView3, ArrayViewMut3, Axis};
+use num_traits::AsPrimitive;
+use std::path::Path;
+
+/// Index of the layer and the index of the channel within that layer
+type ChannelIndex = (usize, usize);
+
+/// Full name of a channel, prefixed with the name of its layer
+fn channel_name(
+    layer: &Layer<AnyChannels<FlatSamples>>,
+    channel: &AnyChannel<FlatSamples>,
+) -> String {
+    match &layer.attributes.layer_name {
+        Some(layer_name) => format!("{layer_name}.{}", channel.name),
+        None => channel.name.to_string(),
+    }
+}
+
+/// ## OpenEXR image, of which any channels can be inpainted.
+///
+/// Channels are read as `f32` and written back in their own sample type,
+/// so `f16` channels stay `f16`.
+///
+/// Only files with a single resolution level are supported, as the smaller mip or rip map
+/// levels would not be inpainted and get lost when saving.
+///
+/// ### Example
+/// ```rust,no_run
+/// use inpaint::{ExrImage, TeleaOptions};
+/// use ndarray::Array2;
+///
+/// let mut image = ExrImage::open("plate.exr").unwrap();
+/// let mask = Array2::<f32>::zeros((1080, 1920));
+///
+/// image
+///     .telea_inpaint(&["R", "G", "B", "depth.Z"], &mask.view(), &TeleaOptions::new(5))
+///     .unwrap();
+/// image.save("clean.exr").unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct ExrImage {
+    image: FlatImage,
+}
+
+impl From<FlatImage> for ExrImage {
+    fn from(image: FlatImage) -> Self {
+        Self { image }
+    }
+}
+
+impl ExrImage {
+    /// Read all layers, channels and attributes of the file.
+    ///
+    /// Files with mip or rip map levels are rejected with an error.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let meta_data = MetaData::read_from_file(&path, false)?;
+        let multiple_levels = meta_data.headers.iter().any(|header| {
+            matches!(header.blocks, BlockDescription::Tiles(tiles) if tiles.level_mode != LevelMode::Singular)
+        });
+        if multiple_levels {
+            return Err(::exr::error::Error::NotSupported(
+                "images with multiple resolution levels".into(),
+            )
+            .into());
+        }
+
+        Ok(Self {
+            image: read_all_flat_layers_from_file(path)?,
+        })
+    }
+
+    /// Write all layers, channels and attributes to the file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        Ok(self.image.write().to_file(path)?)
+    }
+
+    /// The image as represented by the `exr` crate.
+    pub fn as_exr(&self) -> &FlatImage {
+        &self.image
+    }
+
+    /// Consume the image, returning the representation of the `exr` crate.
+    pub fn into_exr(self) -> FlatImage {
+        self.image
+    }
+
+    /// Full names of the channels of all layers.
+    pub fn channel_names(&self) -> Vec<String> {
+        self.image
+            .layer_data
+            .iter()
+            .flat_map(|layer| {
+                layer
+                    .channel_data
+                    .list
+                    .iter()
+                    .map(|channel| channel_name(layer, channel))
+            })
+            .collect()
+    }
+
+    /// Find the layer and channel index of every name, returning them with the shared shape.
+    fn find_channels(&self, names: &[&str]) -> Result<(Vec<ChannelIndex>, (usize, usize))> {
+        let mut indices = Vec::with_capacity(names.len());
+        let mut shape = None;
+        for name in names {
+            let (layer_index, channel_index) = self
+                .image
+                .layer_data
+                .iter()
+                .enumerate()
+                .find_map(|(layer_index, layer)| {
+                    layer
+                        .channel_data
+                        .list
+                        .iter()
+                        .position(|channel| channel_name(layer, channel) == *name)
+                        .map(|channel_index| (layer_index, channel_index))
+                })
+                .ok_or_else(|| Error::ChannelNotFound(name.to_string()))?;
+
+            let layer = &self.image.layer_data[layer_index];
+            let sampling = layer.channel_data.list[channel_index].sampling;
+            let layer_shape = (layer.size.height(), layer.size.width());
+            if sampling.x() != 1 || sampling.y() != 1 {
+                return Err(Error::DimensionMismatch);
+            }
+            if *shape.get_or_insert(layer_shape) != layer_shape {
+                return Err(Error::DimensionMismatch);
+            }
+            indices.push((layer_index, channel_index));
+        }
+        Ok((indices, shape.ok_or(Error::NoData)?))
+    }
+
+    /// ## Read the named channels into an array, with a channel per name.
+    ///
+    /// All channels need to have the same resolution.
+    pub fn channels(&self, names: &[&str]) -> Result<Array3<f32>> {
+        let (indices, (height, width)) = self.find_channels(names)?;
+        let mut array = Array3::zeros((height, width, names.len()));
+        for (mut target, (layer_index, channel_index)) in array.axis_iter_mut(Axis(2)).zip(indices)
+        {
+            let samples =
+                &self.image.layer_data[layer_index].channel_data.list[channel_index].sample_data;
+            let target = target.iter_mut();
+            match samples {
+                FlatSamples::F16(values) => target
+                    .zip(values)
+                    .for_each(|(target, value)| *target = value.to_f32()),
+                FlatSamples::F32(values) => target
+                    .zip(values)
+                    .for_each(|(target, value)| *target = *value),
+                FlatSamples::U32(values) => target
+                    .zip(values)
+                    .for_each(|(target, value)| *target = value.as_()),
+            }
+        }
+        Ok(array)
+    }
+
+    /// ## Write the array into the named channels, in the sample type of every channel.
+    ///
+    /// The array needs a channel per name, with the resolution of the channels.
+    pub fn set_channels(&mut self, names: &[&str], array: &ArrayView3<f32>) -> Result<()> {
+        self.write_channels(names, array, None)
+    }
+
+    /// Write the pixels of the array into the channels, only where `written` is true if provided
+    fn write_channels(
+        &mut self,
+        names: &[&str],
+        array: &ArrayView3<f32>,
+        written: Option<&[bool]>,
+    ) -> Result<()> {
+        let (indices, shape) = self.find_channels(names)?;
+        if array.dim() != (shape.0, shape.1, names.len()) {
+            return Err(Error::DimensionMismatch);
+        }
+
+        let is_written = |index: usize| written.is_none_or(|written| written[index]);
+        for (source, (layer_index, channel_index)) in array.axis_iter(Axis(2)).zip(indices) {
+            let samples = &mut self.image.layer_data[layer_index].channel_data.list[channel_index]
+                .sample_data;
+            let pixels = source
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| is_written(*index));
+            match samples {
+                FlatSamples::F16(values) => {
+                    for (index, value) in pixels {
+                        values[index] = f16::from_f32(*value);
+                    }
+                }
+                FlatSamples::F32(values) => {
+                    for (index, value) in pixels {
+                        values[index] = *value;
+                    }
+                }
+                FlatSamples::U32(values) => {
+                    let conversion = OutputConversion::between::<u32, u32>();
+                    for (index, value) in pixels {
+                        values[index] = conversion.convert(*value);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// ## Inpaint the named channels according to the mask provided.
+    ///
+    /// The channels are inpainted together, like the channels of an image, so they all need
+    /// to have the resolution of the mask. Only the masked pixels of the channels are changed.
+    ///
+    /// ### Arguments:
+    ///
+    /// * `names`: full names of the channels to inpaint, like `R` or `depth.Z`.
+    /// * `mask`: mask that defines the region that will be inpainted
+    /// * `options`: options to configure the algorithm.
+    pub fn telea_inpaint<MaskType>(
+        &mut self,
+        names: &[&str],
+        mask: &ArrayView2<MaskType>,
+        options: &TeleaOptions,
+    ) -> Result<()>
+    where
+        MaskType: AsPrimitive<f32> + Copy + 'static,
+    {
+        self.inpaint_channels(names, mask, |array, mask| {
+            telea_inpaint_with_options(array, mask, options)
+        })
+    }
+
+    /// ## Inpaint the named channels on a pyramid of resolutions, like [`crate::telea_inpaint_pyramid`].
+    ///
+    /// The same as [`ExrImage::telea_inpaint`] otherwise, so only the masked pixels change.
+    ///
+    /// ### Arguments:
+    ///
+    /// * `names`: full names of the channels to inpaint, like `R` or `depth.Z`.
+    /// * `mask`: mask that defines the region that will be inpainted
+    /// * `options`: options to configure the levels and the algorithm per level.
+    pub fn telea_inpaint_pyramid<MaskType>(
+        &mut self,
+        names: &[&str],
+        mask: &ArrayView2<MaskType>,
+        options: &PyramidOptions,
+    ) -> Result<()>
+    where
+        MaskType: AsPrimitive<f32> + Copy + 'static,
+    {
+        self.inpaint_channels(names, mask, |array, mask| {
+            telea_inpaint_pyramid(array, mask, options)
+        })
+    }
+
+    /// Inpaint the named channels as a single array, writing back only the masked pixels
+    fn inpaint_channels<MaskType>(
+        &mut self,
+        names: &[&str],
+        mask: &ArrayView2<MaskType>,
+        inpaint: impl FnOnce(&mut ArrayViewMut3<f32>, &ArrayView2<MaskType>) -> Result<()>,
+    ) -> Result<()>
+    where
+        MaskType: AsPrimitive<f32> + Copy + 'static,
+    {
+        let mut array = self.channels(names)?;
+        inpaint(&mut array.view_mut(), mask)?;
+
+        let masked: Vec<bool> = mask.iter().map(|value| is_masked(*value)).collect();
+        self.write_channels(names, &array.view(), Some(&masked))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::exr::prelude::{
+        AnyChannel, Encoding, Image, ImageAttributes, LayerAttributes, SmallVec, Text, Vec2,
+        attribute::AttributeValue,
+    };
+    use ndarray::{Array2, s};
+
+    /// Image with a beauty layer of half and full precision channels and a depth layer
+    fn test_image() -> FlatImage {
+        let size = Vec2(40, 30);
+        let pixels = size.area();
+        let channel = |name: &str, samples| AnyChannel::new(name, samples);
+        let rgb = Layer::new(
+            size,
+            LayerAttributes::named("beauty"),
+            Encoding::FAST_LOSSLESS,
+            AnyChannels::sort(SmallVec::from_vec(vec![
+                channel(
+                    "R",
+                    FlatSamples::F16(
+                        (0..pixels)
+                            .map(|i| f16::from_f32(i as f32 / 1200.0))
+                            .collect(),
+                    ),
+                ),
+                channel("G", FlatSamples::F16(vec![f16::from_f32(0.5); pixels])),
+                channel("B", FlatSamples::F32(vec![0.25; pixels])),
+            ])),
+        );
+        let depth = Layer::new(
+            size,
+            LayerAttributes::named("depth"),
+            Encoding::FAST_LOSSLESS,
+            AnyChannels::sort(SmallVec::from_vec(vec![channel(
+                "Z",
+                FlatSamples::F32((0..pixels).map(|i| i as f32).collect()),
+            )])),
+        );
+        let mut image = Image::from_layers(
+            ImageAttributes::with_size(size),
+            SmallVec::from_vec(vec![rgb, depth]),
+        );
+        image.layer_data[1].attributes.other.insert(
+            Text::from("studio"),
+            AttributeValue::Text(Text::from("inpaint")),
+        );
+        image
+    }
+
+    #[test]
+    fn test_channel_names() {
+        let image = ExrImage::from(test_image());
+        assert_eq!(
+            image.channel_names(),
+            ["beauty.B", "beauty.G", "beauty.R", "depth.Z"]
+        );
+
+        let array = image.channels(&["beauty.R", "depth.Z"]).unwrap();
+        assert_eq!(array.dim(), (30, 40, 2));
+        assert_eq!(array[[1, 2, 1]], 42.0);
+        assert!(matches!(
+            image.channels(&["depth.X"]),
+            Err(Error::ChannelNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_open_rejects_multiple_levels() {
+        use ::exr::math::RoundingMode;
+        use ::exr::prelude::{Blocks, Levels};
+
+        let size = Vec2(16, 16);
+        let levels = [16, 8, 4, 2, 1]
+            .map(|size| FlatSamples::F32(vec![0.5; size * size]))
+            .to_vec();
+        let layer = Layer::new(
+            size,
+            LayerAttributes::default(),
+            Encoding {
+                blocks: Blocks::Tiles(Vec2(8, 8)),
+                ..Encoding::FAST_LOSSLESS
+            },
+            AnyChannels::sort(SmallVec::from_vec(vec![AnyChannel::new(
+                "Y",
+                Levels::Mip {
+                    rounding_mode: RoundingMode::Down,
+                    level_data: levels,
+                },
+            )])),
+        );
+        let path = std::env::temp_dir().join(format!("inpaint-mip-{}.exr", std::process::id()));
+        Image::from_layer(layer).write().to_file(&path).unwrap();
+
+        let result = ExrImage::open(&path);
+        std::fs::remove_file(&path).ok();
+        assert!(matches!(
+            result,
+            Err(Error::Exr(::exr::error::Error::NotSupported(_)))
+        ));
+    }
+
+    #[test]
+    fn test_inpaint_pyramid_changes_masked_pixels() {
+        let mut image = ExrImage::from(test_image());
+        let mut mask = Array2::<u8>::zeros((30, 40));
+        mask.slice_mut(s![5..25, 5..25]).fill(255);
+        let original = image.channels(&["beauty.R", "depth.Z"]).unwrap();
+        image
+            .telea_inpaint_pyramid(
+                &["beauty.R", "depth.Z"],
+                &mask.view(),
+                &PyramidOptions::new(2, 3),
+            )
+            .unwrap();
+
+        let result = image.channels(&["beauty.R", "depth.Z"]).unwrap();
+        for ((y, x, channel), value) in result.indexed_iter() {
+            if mask[[y, x]] == 0 {
+                assert_eq!(*value, original[[y, x, channel]]);
+            }
+        }
+        assert_ne!(result[[15, 15, 1]], original[[15, 15, 1]]);
+    }
+
+    #[test]
+    fn test_inpaint_preserves_other_channels() {
+        let path = std::env::temp_dir().join(format!("inpaint-{}.exr", std::process::id()));
+        ExrImage::from(test_image()).save(&path).unwrap();
+
+        let mut image = ExrImage::open(&path).unwrap();
+        let mut mask = Array2::<u8>::zeros((30, 40));
+        mask.slice_mut(s![10..20, 10..20]).fill(255);
+        let original = image
+            .channels(&["beauty.R", "beauty.G", "beauty.B", "depth.Z"])
+            .unwrap();
+        image
+            .telea_inpaint(
+                &["beauty.R", "depth.Z"],
+                &mask.view(),
+                &TeleaOptions::new(5),
+            )
+            .unwrap();
+        image.save(&path).unwrap();
+
+        let image = ExrImage::open(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        let result = image
+            .channels(&["beauty.R", "beauty.G", "beauty.B", "depth.Z"])
+            .unwrap();
+        for ((y, x, channel), value) in result.indexed_iter() {
+            let inpainted = mask[[y, x]] > 0 && (channel == 0 || channel == 3);
+            if !inpainted {
+                assert_eq!(*value, original[[y, x, channel]]);
+            }
+        }
+        assert_ne!(result[[15, 15, 3]], original[[15, 15, 3]]);
+        assert!(matches!(
+            image.as_exr().layer_data[0].channel_data.list[2].sample_data,
+            FlatSamples::F16(_)
+        ));
+        assert_eq!(
+            image.as_exr().layer_data[1]
+                .attributes
+                .other
+                .get(&Text::from("studio")),
+            Some(&AttributeValue::Text(Text::from("inpaint")))
+        );
+    }
+}
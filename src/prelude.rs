@@ -1,6 +1,6 @@
 #[cfg(feature = "image")]
 mod image {
-    use crate::telea_inpaint;
+    use crate::{OutputConversion, TeleaOptions, telea_inpaint, telea_inpaint_into};
     use image::{ImageBuffer, Luma, Pixel, Primitive};
     use image_ndarray::prelude::*;
 
@@ -23,6 +23,18 @@ mod image {
         where
             P: Clone + Copy + AsPrimitive<f32> + Primitive,
             f32: AsPrimitive<P>;
+
+        /// Inpaint a copy of the image with provided mask using Telea algorithm,
+        /// leaving the image itself untouched.
+        fn telea_inpainted<P>(
+            &self,
+            mask: &ImageBuffer<Luma<P>, Vec<P>>,
+            radius: i32,
+        ) -> Result<Self>
+        where
+            Self: Sized,
+            P: Clone + Copy + AsPrimitive<f32> + Primitive,
+            f32: AsPrimitive<P>;
     }
 
     #[cfg(feature = "image")]
@@ -51,6 +63,26 @@ mod image {
 
             Ok(())
         }
+
+        fn telea_inpainted<P>(
+            &self,
+            mask: &ImageBuffer<Luma<P>, Vec<P>>,
+            radius: i32,
+        ) -> Result<Self>
+        where
+            P: Clone + Copy + AsPrimitive<f32> + Primitive,
+            f32: AsPrimitive<P>,
+        {
+            let mut output = ImageBuffer::new(self.width(), self.height());
+            telea_inpaint_into(
+                &self.as_ndarray(),
+                &mask.as_ndarray().slice(ndarray::s![.., .., 0]),
+                &mut output.as_ndarray_mut(),
+                &TeleaOptions::new(radius),
+                &OutputConversion::between::<ImageContainer, ImageContainer>(),
+            )?;
+            Ok(output)
+        }
     }
 
     #[cfg(test)]
@@ -123,6 +155,24 @@ mod image {
                 }
             };
         }
+
+        #[test]
+        fn test_inpainted_image() {
+            let image = image::open("./test/images/input/bird.png")
+                .unwrap()
+                .to_rgb8();
+            let mask = image::open("./test/images/mask/thin.png")
+                .unwrap()
+                .to_luma8();
+
+            let output = image.telea_inpainted(&mask, 5).unwrap();
+
+            let mut expected = image.clone();
+            expected.telea_inpaint(&mask, 5).unwrap();
+            assert_eq!(output, expected);
+            assert_ne!(output, image);
+        }
+
         create_inpaint_test_cases!(Rgba, f32, rgbaf32);
         create_inpaint_test_cases!(Rgba, u16, rgbau16);
         create_inpaint_test_cases!(Rgb, u16, rgbu16);
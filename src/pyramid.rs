@@ -0,0 +1,427 @@
+/// Multi-resolution inpainting for large holes.
+///
+/// The image is downscaled into a pyramid of levels, which halve the resolution every level.
+/// The coarsest level is inpainted first. Every finer level is seeded with the upsampled
+/// result of the coarser level deep inside the holes, so only a band along the boundary of
+/// the holes is inpainted at that level. This avoids the long streaks that are visible when
+/// large holes are inpainted at full resolution with a small radius.
+use crate::conversion::OutputConversion;
+use crate::distance::euclidean_distance_transform;
+use crate::error::{Error, Result};
+use crate::telea::{TeleaOptions, TeleaWorkspace, is_masked};
+use glam::USizeVec2;
+use ndarray::{Array2, Array3, ArrayView2, ArrayViewMut3, Zip};
+use num_traits::AsPrimitive;
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+
+#[derive(Clone, Debug, PartialEq)]
+/// Options to configure the multi-resolution Telea algorithm.
+///
+/// ### Example
+/// ```rust
+/// use inpaint::{PyramidOptions, TeleaOptions};
+///
+/// // Three levels, with a radius of 3 at full resolution and 5 at the lower resolutions
+/// let options = PyramidOptions {
+///     radii: vec![3, 5, 5],
+///     ..PyramidOptions::new(3, 5)
+/// };
+/// ```
+pub struct PyramidOptions {
+    /// Amount of levels, including the full resolution. A single level disables the pyramid.
+    pub levels: usize,
+    /// Radius per level, starting at the full resolution.
+    ///
+    /// Levels without a radius use the radius of `options`.
+    pub radii: Vec<i32>,
+    /// Options that are used to inpaint every level.
+    pub options: TeleaOptions,
+}
+
+impl PyramidOptions {
+    /// Initialize options with the provided levels and the same radius for every level.
+    pub fn new(levels: usize, radius: i32) -> Self {
+        Self {
+            levels,
+            radii: Vec::new(),
+            options: TeleaOptions::new(radius),
+        }
+    }
+
+    /// Options that are used to inpaint the provided level
+    fn level_options(&self, level: usize) -> TeleaOptions {
+        TeleaOptions {
+            radius: self
+                .radii
+                .get(level)
+                .copied()
+                .unwrap_or(self.options.radius),
+            ..self.options.clone()
+        }
+    }
+}
+
+impl Default for PyramidOptions {
+    fn default() -> Self {
+        Self::new(3, 5)
+    }
+}
+
+/// Halve the resolution of the image and mask.
+///
+/// Every pixel is the average of the known pixels it covers, and is only masked when all
+/// pixels it covers are masked.
+fn downscale(image: &Array3<f32>, mask: &Array2<u8>) -> (Array3<f32>, Array2<u8>) {
+    let (height, width, channels) = image.dim();
+    let resolution = USizeVec2::new(width.div_ceil(2), height.div_ceil(2));
+    let mut downscaled = Array3::zeros((resolution.y, resolution.x, channels));
+    let mut downscaled_mask = Array2::zeros((resolution.y, resolution.x));
+
+    for y in 0..resolution.y {
+        for x in 0..resolution.x {
+            let mut known = 0;
+            for source_y in 2 * y..(2 * y + 2).min(height) {
+                for source_x in 2 * x..(2 * x + 2).min(width) {
+                    if mask[[source_y, source_x]] != 0 {
+                        continue;
+                    }
+                    known += 1;
+                    for channel in 0..channels {
+                        downscaled[[y, x, channel]] += image[[source_y, source_x, channel]];
+                    }
+                }
+            }
+            if known == 0 {
+                downscaled_mask[[y, x]] = 1;
+                continue;
+            }
+            for channel in 0..channels {
+                downscaled[[y, x, channel]] /= known as f32;
+            }
+        }
+    }
+    (downscaled, downscaled_mask)
+}
+
+/// Bilinearly sample the coarser level at the center of a pixel of the finer level
+fn sample_upscaled(coarse: &Array3<f32>, y: usize, x: usize, channel: usize) -> f32 {
+    let (height, width, _) = coarse.dim();
+    let position_y = ((y as f32 + 0.5) * 0.5 - 0.5).max(0.0);
+    let position_x = ((x as f32 + 0.5) * 0.5 - 0.5).max(0.0);
+    let y0 = (position_y as usize).min(height - 1);
+    let x0 = (position_x as usize).min(width - 1);
+    let y1 = (y0 + 1).min(height - 1);
+    let x1 = (x0 + 1).min(width - 1);
+    let weight_y = position_y - y0 as f32;
+    let weight_x = position_x - x0 as f32;
+
+    let top = coarse[[y0, x0, channel]] * (1.0 - weight_x) + coarse[[y0, x1, channel]] * weight_x;
+    let bottom =
+        coarse[[y1, x0, channel]] * (1.0 - weight_x) + coarse[[y1, x1, channel]] * weight_x;
+    top * (1.0 - weight_y) + bottom * weight_y
+}
+
+/// Fill the pixels deep inside the holes with the upsampled coarser level.
+///
+/// Pixels further away from the known pixels than the seed distance are taken from the
+/// coarser level and are removed from the mask, so only the band along the boundary
+/// remains to be inpainted.
+fn seed_from_coarse(
+    image: &mut Array3<f32>,
+    mask: &mut Array2<u8>,
+    coarse: &Array3<f32>,
+    seed_distance: f32,
+) {
+    let (height, width, channels) = image.dim();
+    let distances =
+        euclidean_distance_transform(USizeVec2::new(width, height), |y, x| mask[[y, x]] == 0);
+
+    for ((y, x), distance) in distances.indexed_iter() {
+        if mask[[y, x]] == 0 || *distance <= seed_distance {
+            continue;
+        }
+        for channel in 0..channels {
+            image[[y, x, channel]] = sample_upscaled(coarse, y, x, channel);
+        }
+        mask[[y, x]] = 0;
+    }
+}
+
+/// ## Inpaint the input image according to the mask provided, using a pyramid of resolutions.
+///
+/// The lower resolutions are inpainted first and guide the inpainting of the higher
+/// resolutions, which gives smoother results for large holes than [`crate::telea_inpaint`].
+///
+/// ### Arguments:
+///
+/// * `image`: array to inpaint.
+/// * `mask`: mask that defines the region that will be inpainted
+/// * `options`: options to configure the levels and the algorithm per level.
+///
+/// ### Example
+/// ```rust
+/// use inpaint::{PyramidOptions, telea_inpaint_pyramid};
+/// use ndarray::{Array2, Array3};
+///
+/// let mut input_image = Array3::from_elem((1080, 1920, 4), 0u8);
+/// let mask = Array2::from_elem((1080, 1920), 0u8);
+///
+/// telea_inpaint_pyramid(&mut input_image.view_mut(), &mask.view(), &PyramidOptions::new(3, 5))
+///     .unwrap();
+/// ```
+pub fn telea_inpaint_pyramid<ImageType, MaskType>(
+    image: &mut ArrayViewMut3<ImageType>,
+    mask: &ArrayView2<MaskType>,
+    options: &PyramidOptions,
+) -> Result<()>
+where
+    ImageType: AsPrimitive<f32> + Copy,
+    f32: num_traits::AsPrimitive<ImageType>,
+    MaskType: AsPrimitive<f32> + Copy + 'static,
+{
+    if image.shape()[1] != mask.ncols() || image.shape()[0] != mask.nrows() {
+        return Err(Error::DimensionMismatch);
+    }
+
+    let mut images: Vec<Array3<f32>> = vec![image.mapv(|value| value.as_())];
+    let mut masks = vec![mask.mapv(|value| is_masked(value) as u8)];
+    for _ in 1..options.levels {
+        let (finer_image, finer_mask) = (&images[images.len() - 1], &masks[masks.len() - 1]);
+        if finer_mask.nrows() <= 1 && finer_mask.ncols() <= 1 {
+            break;
+        }
+        let (coarse_image, coarse_mask) = downscale(finer_image, finer_mask);
+        images.push(coarse_image);
+        masks.push(coarse_mask);
+    }
+
+    let mut workspace = TeleaWorkspace::new();
+    let mut coarse: Option<Array3<f32>> = None;
+    for (level, (mut level_image, mut level_mask)) in
+        images.into_iter().zip(masks).enumerate().rev()
+    {
+        let level_options = options.level_options(level);
+        if let Some(coarse) = &coarse {
+            let seed_distance = 2.0 * level_options.radius.max(1) as f32;
+            seed_from_coarse(&mut level_image, &mut level_mask, coarse, seed_distance);
+        }
+        workspace.telea_inpaint::<f32, u8>(
+            &mut level_image.view_mut(),
+            &level_mask.view(),
+            &level_options,
+        )?;
+        coarse = Some(level_image);
+    }
+
+    if let Some(inpainted) = coarse {
+        let conversion = OutputConversion::between::<ImageType, ImageType>();
+        Zip::from(image.rows_mut())
+            .and(inpainted.rows())
+            .and(mask)
+            .for_each(|mut pixel, inpainted, value| {
+                if !is_masked(*value) {
+                    return;
+                }
+                for (target, inpainted) in pixel.iter_mut().zip(inpainted) {
+                    *target = conversion.convert(*inpainted);
+                }
+            });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telea::telea_inpaint_with_options;
+    use image::ImageFormat;
+    use ndarray::s;
+    use rstest::rstest;
+
+    /// Sum of the absolute second differences of the masked pixels
+    fn roughness<T: AsPrimitive<f32>>(image: &Array3<T>, mask: &Array2<u8>) -> f32 {
+        let (height, width, channels) = image.dim();
+        let mut sum = 0.0;
+        for y in 1..height - 1 {
+            for x in 1..width - 1 {
+                if mask[[y, x]] == 0 {
+                    continue;
+                }
+                for channel in 0..channels {
+                    let value = |y: usize, x: usize| image[[y, x, channel]].as_();
+                    sum += (value(y, x - 1) - 2.0 * value(y, x) + value(y, x + 1)).abs()
+                        + (value(y - 1, x) - 2.0 * value(y, x) + value(y + 1, x)).abs();
+                }
+            }
+        }
+        sum
+    }
+
+    #[test]
+    fn test_downscale() {
+        let image = Array3::from_shape_fn((3, 4, 1), |(y, x, _)| (y * 4 + x) as f32);
+        let mut mask = Array2::zeros((3, 4));
+        mask[[0, 0]] = 1;
+        mask.slice_mut(s![0..2, 2..4]).fill(1);
+
+        let (downscaled, downscaled_mask) = downscale(&image, &mask);
+
+        assert_eq!(downscaled_mask, ndarray::array![[0, 1], [0, 0]]);
+        assert_eq!(downscaled[[0, 0, 0]], (1.0 + 4.0 + 5.0) / 3.0);
+        assert_eq!(downscaled[[1, 0, 0]], (8.0 + 9.0) / 2.0);
+        assert_eq!(downscaled[[1, 1, 0]], (10.0 + 11.0) / 2.0);
+    }
+
+    #[test]
+    fn test_single_level_matches_telea() {
+        let image = Array3::from_shape_fn((40, 50, 3), |(y, x, channel)| {
+            ((y * 3 + x * 5 + channel) % 13) as f32 / 13.0
+        });
+        let mut mask = Array2::<f32>::zeros((40, 50));
+        mask.slice_mut(s![10..25, 15..30]).fill(1.0);
+
+        let mut expected = image.clone();
+        telea_inpaint_with_options(
+            &mut expected.view_mut(),
+            &mask.view(),
+            &TeleaOptions::new(4),
+        )
+        .unwrap();
+        let mut result = image.clone();
+        telea_inpaint_pyramid(
+            &mut result.view_mut(),
+            &mask.view(),
+            &PyramidOptions::new(1, 4),
+        )
+        .unwrap();
+
+        assert_eq!(result, expected);
+    }
+
+    #[rstest]
+    #[case(
+        include_bytes!("../test/images/input/bird.png"),
+        include_bytes!("../test/images/mask/large.png"),
+    )]
+    #[case(
+        include_bytes!("../test/images/input/pizza.png"),
+        include_bytes!("../test/images/mask/rectangle.png"),
+    )]
+    /// Only masked pixels should change, with fewer streaks than a single level
+    fn test_inpaint_pyramid(#[case] input: &[u8], #[case] mask: &[u8]) {
+        let input = image::load_from_memory_with_format(input, ImageFormat::Png)
+            .unwrap()
+            .to_rgb8();
+        let mask = image::load_from_memory_with_format(mask, ImageFormat::Png)
+            .unwrap()
+            .to_luma8();
+        let shape = (input.height() as usize, input.width() as usize);
+        let image = Array3::from_shape_vec((shape.0, shape.1, 3), input.into_raw()).unwrap();
+        let mask = Array2::from_shape_vec(shape, mask.into_raw()).unwrap();
+
+        let options = PyramidOptions {
+            radii: vec![2, 3],
+            ..PyramidOptions::new(4, 5)
+        };
+        let mut result = image.clone();
+        telea_inpaint_pyramid(&mut result.view_mut(), &mask.view(), &options).unwrap();
+
+        let mut changed = 0;
+        Zip::from(result.rows())
+            .and(image.rows())
+            .and(&mask)
+            .for_each(|result, image, mask| {
+                if *mask == 0 {
+                    assert_eq!(result, image);
+                } else if result != image {
+                    changed += 1;
+                }
+            });
+        assert!(changed > 0);
+
+        // Streaks show up as sharp changes within the holes, which the coarse levels smooth
+        let mut single = image.clone();
+        telea_inpaint_with_options(&mut single.view_mut(), &mask.view(), &TeleaOptions::new(2))
+            .unwrap();
+        assert!(roughness(&result, &mask) < 0.8 * roughness(&single, &mask));
+    }
+
+    #[test]
+    /// Deep pixels of the holes are taken from the coarse level, the band along the boundary
+    /// remains masked for the finer level
+    fn test_seed_from_coarse() {
+        let mut image = Array3::<f32>::zeros((20, 20, 1));
+        let mut mask = Array2::<u8>::zeros((20, 20));
+        mask.slice_mut(s![2..18, 2..18]).fill(1);
+        let coarse = Array3::from_elem((10, 10, 1), 0.5);
+
+        seed_from_coarse(&mut image, &mut mask, &coarse, 4.0);
+
+        assert_eq!(mask.slice(s![7..13, 7..13]).sum(), 0);
+        assert!(
+            image
+                .slice(s![7..13, 7..13, 0])
+                .iter()
+                .all(|value| *value == 0.5)
+        );
+        assert!(mask.slice(s![2..18, 2..6]).iter().all(|value| *value == 1));
+        assert_eq!(image.slice(s![2..18, 2..6, 0]).sum(), 0.0);
+    }
+
+    #[test]
+    /// Large holes in smooth images are closer to the ground truth and smoother than with a
+    /// single level at the same radius
+    fn test_pyramid_large_hole_accuracy() {
+        let truth = Array3::from_shape_fn((128, 128, 1), |(y, x, _)| {
+            let (y, x) = (y as f32, x as f32);
+            0.2 + 0.5 * x / 128.0 + 0.2 * (y / 40.0).sin() + 0.1 * (x / 50.0 + y / 60.0).cos()
+        });
+        let mut mask = Array2::<u8>::zeros((128, 128));
+        mask.slice_mut(s![32..96, 24..104]).fill(255);
+        let error = |result: &Array3<f32>| {
+            let mut sum = 0.0;
+            Zip::from(result)
+                .and(&truth)
+                .and(mask.view().insert_axis(ndarray::Axis(2)))
+                .for_each(|result, truth, mask| {
+                    if *mask > 0 {
+                        sum += (result - truth) * (result - truth);
+                    }
+                });
+            (sum / mask.iter().filter(|value| **value > 0).count() as f32).sqrt()
+        };
+
+        let mut single = truth.clone();
+        telea_inpaint_with_options(&mut single.view_mut(), &mask.view(), &TeleaOptions::new(3))
+            .unwrap();
+        let mut pyramid = truth.clone();
+        telea_inpaint_pyramid(
+            &mut pyramid.view_mut(),
+            &mask.view(),
+            &PyramidOptions::new(4, 3),
+        )
+        .unwrap();
+
+        assert!(error(&pyramid) < 0.75 * error(&single));
+        assert!(roughness(&pyramid, &mask) < roughness(&single, &mask));
+    }
+
+    #[test]
+    fn test_pyramid_dimension_mismatch() {
+        let mut image = Array3::<f32>::zeros((10, 10, 1));
+        let mask = Array2::<f32>::zeros((10, 11));
+        assert!(matches!(
+            telea_inpaint_pyramid(
+                &mut image.view_mut(),
+                &mask.view(),
+                &PyramidOptions::default()
+            ),
+            Err(Error::DimensionMismatch)
+        ));
+    }
+}
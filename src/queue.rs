@@ -0,0 +1,355 @@
+/// Priority queues that are used to store the pixels of the narrow band.
+///
+/// The narrow band is popped in order of the distance to the boundary. Besides a binary heap,
+/// a bucket queue is available which stores the pixels in buckets of quantized distance.
+/// As the distances that are pushed only grow slightly compared to the popped distance,
+/// the buckets form a ring that is reused while marching. The rare distances beyond the
+/// ring are kept in a binary heap.
+use crate::telea::NarrowBandQueue;
+use core::cmp::Ordering;
+use core::cmp::Reverse;
+use glam::USizeVec2;
+#[cfg(feature = "std")]
+use std::collections::BinaryHeap;
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+#[cfg(not(feature = "std"))]
+use alloc::{collections::BinaryHeap, vec::Vec};
+
+/// Width of a single bucket in distance
+const BUCKET_WIDTH: f32 = 1.0 / 16.0;
+/// Amount of buckets in the ring, which covers a distance of 4 pixels
+const BUCKET_COUNT: usize = 64;
+
+#[derive(Debug, Clone, Copy)]
+/// Item for in the NarrowBand.
+///
+/// It has a priority assigned which is the most important.
+/// After that the y value is used for weight and then the x value.
+pub(crate) struct QueueItem {
+    pub priority: f32,
+    pub coordinates: USizeVec2,
+}
+
+impl QueueItem {
+    /// Initialize item from
+    pub fn new(cost: f32, coordinates: USizeVec2) -> Self {
+        Self {
+            priority: cost,
+            coordinates,
+        }
+    }
+}
+
+impl Ord for QueueItem {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let cost_ordering = self
+            .priority
+            .partial_cmp(&other.priority)
+            .unwrap_or(Ordering::Equal);
+
+        match cost_ordering {
+            Ordering::Equal => match self.coordinates.y.cmp(&other.coordinates.y) {
+                Ordering::Equal => self.coordinates.x.cmp(&other.coordinates.x),
+                ordering => ordering,
+            },
+            _ => cost_ordering,
+        }
+    }
+}
+
+impl PartialOrd for QueueItem {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for QueueItem {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority
+    }
+}
+
+impl Eq for QueueItem {}
+
+#[derive(Debug, Clone)]
+/// Queue that stores the items in a ring of buckets with quantized priority.
+///
+/// Items are popped in the same order as a binary heap would do. Only the bucket that is
+/// popped from gets sorted, which is cheap as the buckets are small.
+/// Items with a priority below the current bucket are stored in the current bucket, while
+/// items beyond the range of the ring are kept in a binary heap until the ring reaches them.
+pub(crate) struct BucketQueue {
+    buckets: Vec<Vec<QueueItem>>,
+    /// Items with a priority beyond the range of the ring
+    overflow: BinaryHeap<Reverse<QueueItem>>,
+    /// Absolute index of the bucket that is popped from
+    current: usize,
+    /// Absolute index of the bucket that has been sorted for popping
+    sorted: Option<usize>,
+    /// Amount of items in the ring
+    len: usize,
+}
+
+impl Default for BucketQueue {
+    fn default() -> Self {
+        Self {
+            buckets: (0..BUCKET_COUNT).map(|_| Vec::new()).collect(),
+            overflow: BinaryHeap::new(),
+            current: 0,
+            sorted: None,
+            len: 0,
+        }
+    }
+}
+
+impl BucketQueue {
+    /// Absolute index of the bucket for the priority
+    fn key(priority: f32) -> usize {
+        (priority / BUCKET_WIDTH) as usize
+    }
+
+    /// Add an item to the bucket of its priority
+    pub fn push(&mut self, item: QueueItem) {
+        let key = Self::key(item.priority).max(self.current);
+        if key - self.current >= BUCKET_COUNT {
+            self.overflow.push(Reverse(item));
+            return;
+        }
+        self.push_bucket(key, item);
+    }
+
+    /// Add an item to the bucket with the absolute index, which has to be within the ring
+    fn push_bucket(&mut self, key: usize, item: QueueItem) {
+        if self.sorted == Some(key) {
+            self.sorted = None;
+        }
+        self.buckets[key % BUCKET_COUNT].push(item);
+        self.len += 1;
+    }
+
+    /// Remove the item with the lowest priority
+    pub fn pop(&mut self) -> Option<QueueItem> {
+        if self.len == 0 {
+            let Reverse(next) = self.overflow.peek()?;
+            self.current = Self::key(next.priority);
+        }
+        while let Some(Reverse(next)) = self.overflow.peek() {
+            let key = Self::key(next.priority);
+            if key - self.current >= BUCKET_COUNT {
+                break;
+            }
+            if let Some(Reverse(item)) = self.overflow.pop() {
+                self.push_bucket(key, item);
+            }
+        }
+        while self.buckets[self.current % BUCKET_COUNT].is_empty() {
+            self.current += 1;
+        }
+
+        let bucket = &mut self.buckets[self.current % BUCKET_COUNT];
+        if self.sorted != Some(self.current) {
+            bucket.sort_by(|a, b| b.cmp(a));
+            self.sorted = Some(self.current);
+        }
+        self.len -= 1;
+        bucket.pop()
+    }
+
+    /// Remove all items, while keeping the allocated buckets
+    pub fn clear(&mut self) {
+        for bucket in self.buckets.iter_mut() {
+            bucket.clear();
+        }
+        self.overflow.clear();
+        self.current = 0;
+        self.sorted = None;
+        self.len = 0;
+    }
+
+    /// Check if the queue does not contain any items
+    pub fn is_empty(&self) -> bool {
+        self.len == 0 && self.overflow.is_empty()
+    }
+
+    /// Replace the contents with the contents of the other queue, reusing the allocations
+    pub fn copy_from(&mut self, other: &Self) {
+        for (bucket, other_bucket) in self.buckets.iter_mut().zip(other.buckets.iter()) {
+            bucket.clear();
+            bucket.extend_from_slice(other_bucket);
+        }
+        self.overflow.clear();
+        self.overflow.extend(other.overflow.iter().cloned());
+        self.current = other.current;
+        self.sorted = other.sorted;
+        self.len = other.len;
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Queue that contains the pixels of the narrow band
+pub(crate) enum NarrowBand {
+    Buckets(BucketQueue),
+    Heap(BinaryHeap<Reverse<QueueItem>>),
+}
+
+impl Default for NarrowBand {
+    fn default() -> Self {
+        Self::Buckets(BucketQueue::default())
+    }
+}
+
+impl NarrowBand {
+    /// Clear the queue and make sure it uses the requested queue structure
+    pub fn reset(&mut self, queue: NarrowBandQueue) {
+        match (queue, &mut *self) {
+            (NarrowBandQueue::Buckets, Self::Buckets(buckets)) => buckets.clear(),
+            (NarrowBandQueue::BinaryHeap, Self::Heap(heap)) => heap.clear(),
+            (NarrowBandQueue::Buckets, _) => *self = Self::Buckets(BucketQueue::default()),
+            (NarrowBandQueue::BinaryHeap, _) => *self = Self::Heap(BinaryHeap::new()),
+        }
+    }
+
+    /// Add an item to the queue
+    pub fn push(&mut self, item: QueueItem) {
+        match self {
+            Self::Buckets(buckets) => buckets.push(item),
+            Self::Heap(heap) => heap.push(Reverse(item)),
+        }
+    }
+
+    /// Remove the item with the lowest priority
+    pub fn pop(&mut self) -> Option<QueueItem> {
+        match self {
+            Self::Buckets(buckets) => buckets.pop(),
+            Self::Heap(heap) => heap.pop().map(|item| item.0),
+        }
+    }
+
+    /// Check if the queue does not contain any items
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Self::Buckets(buckets) => buckets.is_empty(),
+            Self::Heap(heap) => heap.is_empty(),
+        }
+    }
+
+    /// Replace the contents with the contents of the other queue, reusing the allocations
+    pub fn copy_from(&mut self, other: &Self) {
+        match (&mut *self, other) {
+            (Self::Buckets(buckets), Self::Buckets(other_buckets)) => {
+                buckets.copy_from(other_buckets)
+            }
+            (Self::Heap(heap), Self::Heap(other_heap)) => {
+                heap.clear();
+                heap.extend(other_heap.iter().cloned());
+            }
+            _ => *self = other.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Generate pseudo random values between 0 and 1 with coordinates
+    fn pseudo_random_items() -> Vec<(f32, USizeVec2)> {
+        let mut seed: u32 = 12345;
+        (0..5000)
+            .map(|_| {
+                seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                let value = (seed >> 16) as f32 / 65535.0;
+                (
+                    value,
+                    USizeVec2::new((seed % 13) as usize, (seed % 7) as usize),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    /// Pushing distances like while marching should pop in the same order as the heap
+    fn test_bucket_queue_matches_heap() {
+        let mut buckets = NarrowBand::default();
+        let mut heap = NarrowBand::default();
+        heap.reset(NarrowBandQueue::BinaryHeap);
+
+        for (x, y) in [(3, 1), (1, 1), (2, 0), (1, 0)] {
+            let item = QueueItem::new(0.0, USizeVec2::new(x, y));
+            buckets.push(item);
+            heap.push(item);
+        }
+
+        let mut pushed = pseudo_random_items().into_iter();
+        while !heap.is_empty() {
+            let expected = heap.pop().unwrap();
+            let popped = buckets.pop().unwrap();
+            assert_eq!(popped.priority, expected.priority);
+            assert_eq!(popped.coordinates, expected.coordinates);
+
+            for _ in 0..2 {
+                if let Some((value, coordinates)) = pushed.next() {
+                    // Mostly further away, but sometimes slightly below the popped item
+                    let item = QueueItem::new(popped.priority + value * 1.5 - 0.1, coordinates);
+                    buckets.push(item);
+                    heap.push(item);
+                }
+            }
+        }
+        assert!(buckets.is_empty());
+        assert!(buckets.pop().is_none());
+    }
+
+    #[test]
+    /// Priorities far beyond the ring should still pop in the same order as the heap
+    fn test_bucket_queue_far_priorities() {
+        let mut buckets = NarrowBand::default();
+        let mut heap = NarrowBand::default();
+        heap.reset(NarrowBandQueue::BinaryHeap);
+        let mut push = |priority: f32, x: usize| {
+            let item = QueueItem::new(priority, USizeVec2::new(x, 0));
+            buckets.push(item);
+            heap.push(item);
+        };
+
+        for (x, priority) in [0.0, 5.0, 3.99, 4.01, 12.5, 1e6, f32::MAX, 9.0, 1e6, 0.5]
+            .into_iter()
+            .enumerate()
+        {
+            push(priority, x);
+        }
+
+        let mut far = [20.0, 6.0, 1e6 - 1.0, f32::MAX, 7.5, 8.0].into_iter();
+        while !heap.is_empty() {
+            let expected = heap.pop().unwrap();
+            let popped = buckets.pop().unwrap();
+            assert_eq!(popped.priority, expected.priority);
+            assert_eq!(popped.coordinates, expected.coordinates);
+
+            if let Some(priority) = far.next() {
+                buckets.push(QueueItem::new(popped.priority + priority, USizeVec2::ZERO));
+                heap.push(QueueItem::new(popped.priority + priority, USizeVec2::ZERO));
+            }
+        }
+        assert!(buckets.is_empty());
+        assert!(buckets.pop().is_none());
+    }
+
+    #[test]
+    fn test_bucket_queue_copy_and_clear() {
+        let mut queue = BucketQueue::default();
+        queue.push(QueueItem::new(2.0, USizeVec2::new(1, 0)));
+        queue.push(QueueItem::new(0.5, USizeVec2::new(0, 0)));
+
+        let mut copy = BucketQueue::default();
+        copy.copy_from(&queue);
+        queue.clear();
+
+        assert!(queue.is_empty());
+        assert_eq!(copy.pop().unwrap().priority, 0.5);
+        assert_eq!(copy.pop().unwrap().priority, 2.0);
+        assert!(copy.is_empty());
+    }
+}
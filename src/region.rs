@@ -0,0 +1,221 @@
+/// Utilities to find the independent regions of a mask.
+///
+/// Regions that are far enough apart don't influence each other while inpainting,
+/// so they can be processed on their own.
+use glam::USizeVec2;
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Rectangular region of an image.
+///
+/// The minimum coordinates are inclusive, while the maximum coordinates are exclusive.
+pub(crate) struct Region {
+    pub min: USizeVec2,
+    pub max: USizeVec2,
+}
+
+impl Region {
+    /// Initialize region from its minimum and (exclusive) maximum coordinates
+    pub fn new(min: USizeVec2, max: USizeVec2) -> Self {
+        Self { min, max }
+    }
+
+    /// Initialize region that only covers the provided pixel
+    pub fn from_coordinates(coordinates: USizeVec2) -> Self {
+        Self::new(coordinates, coordinates + 1)
+    }
+
+    /// Grow the region so that it covers the provided pixel as well
+    pub fn include(&mut self, coordinates: USizeVec2) {
+        self.min = self.min.min(coordinates);
+        self.max = self.max.max(coordinates + 1);
+    }
+
+    /// Smallest region that covers both regions
+    pub fn union(&self, other: &Self) -> Self {
+        Self::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    /// Expand the region by the margin on all sides, limited to the resolution
+    pub fn expand(&self, margin: usize, resolution: USizeVec2) -> Self {
+        Self::new(
+            self.min.saturating_sub(USizeVec2::splat(margin)),
+            (self.max + margin).min(resolution),
+        )
+    }
+
+    /// Region that is covered by both regions, empty when they don't intersect
+    pub fn intersection(&self, other: &Self) -> Self {
+        let min = self.min.max(other.min);
+        Self::new(min, self.max.min(other.max).max(min))
+    }
+
+    /// Check if the pixel is within the region
+    pub fn contains(&self, coordinates: USizeVec2) -> bool {
+        coordinates.cmpge(self.min).all() && coordinates.cmplt(self.max).all()
+    }
+
+    /// Check if both regions share at least one pixel
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.min.x < other.max.x
+            && other.min.x < self.max.x
+            && self.min.y < other.max.y
+            && other.min.y < self.max.y
+    }
+}
+
+/// Find the bounding boxes of all connected masked pixels.
+///
+/// Regions that are closer than twice the separation to each other are merged,
+/// so the returned regions are at least that far apart.
+/// The `visited` buffer is used to keep track of the pixels that have been labeled.
+pub(crate) fn find_regions<F>(
+    resolution: USizeVec2,
+    is_masked: F,
+    separation: usize,
+    visited: &mut Vec<bool>,
+) -> Vec<Region>
+where
+    F: Fn(usize, usize) -> bool,
+{
+    visited.clear();
+    visited.resize(resolution.x * resolution.y, false);
+    let mut stack = Vec::new();
+    let mut components = Vec::new();
+
+    for y in 0..resolution.y {
+        for x in 0..resolution.x {
+            if visited[y * resolution.x + x] || !is_masked(y, x) {
+                continue;
+            }
+            visited[y * resolution.x + x] = true;
+            let start = USizeVec2::new(x, y);
+            let mut component = Region::from_coordinates(start);
+            stack.push(start);
+
+            while let Some(coordinates) = stack.pop() {
+                component.include(coordinates);
+                let neighborhood = Region::from_coordinates(coordinates).expand(1, resolution);
+                for neighbor_y in neighborhood.min.y..neighborhood.max.y {
+                    for neighbor_x in neighborhood.min.x..neighborhood.max.x {
+                        let index = neighbor_y * resolution.x + neighbor_x;
+                        if visited[index] || !is_masked(neighbor_y, neighbor_x) {
+                            continue;
+                        }
+                        visited[index] = true;
+                        stack.push(USizeVec2::new(neighbor_x, neighbor_y));
+                    }
+                }
+            }
+            components.push(component);
+        }
+    }
+
+    merge_regions(components, separation)
+}
+
+/// Merge all regions that are closer than twice the separation to each other.
+///
+/// The order of the regions does not matter, as merged regions are checked again
+/// against all other regions until none of them are close anymore.
+pub(crate) fn merge_regions(components: Vec<Region>, separation: usize) -> Vec<Region> {
+    let mut regions: Vec<Region> = Vec::with_capacity(components.len());
+    for component in components {
+        let mut current = component;
+        let unbounded = USizeVec2::splat(usize::MAX);
+        while let Some(index) = regions
+            .iter()
+            .position(|region| current.expand(separation * 2, unbounded).intersects(region))
+        {
+            current = current.union(&regions.swap_remove(index));
+        }
+        regions.push(current);
+    }
+    regions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_regions_separated() {
+        let resolution = USizeVec2::new(20, 10);
+        let regions = find_regions(
+            resolution,
+            |y, x| (y == 2 && (1..4).contains(&x)) || ((5..8).contains(&y) && x == 15),
+            2,
+            &mut Vec::new(),
+        );
+
+        assert_eq!(regions.len(), 2);
+        assert!(regions.contains(&Region::new(USizeVec2::new(1, 2), USizeVec2::new(4, 3))));
+        assert!(regions.contains(&Region::new(USizeVec2::new(15, 5), USizeVec2::new(16, 8))));
+    }
+
+    #[test]
+    fn test_find_regions_merged() {
+        let resolution = USizeVec2::new(20, 10);
+        let regions = find_regions(
+            resolution,
+            |y, x| (y == 2 && x == 2) || (y == 4 && x == 6) || (y == 9 && x == 19),
+            2,
+            &mut Vec::new(),
+        );
+
+        assert_eq!(regions.len(), 2);
+        assert!(regions.contains(&Region::new(USizeVec2::new(2, 2), USizeVec2::new(7, 5))));
+        assert!(regions.contains(&Region::new(USizeVec2::new(19, 9), USizeVec2::new(20, 10))));
+    }
+
+    #[test]
+    fn test_find_regions_diagonal_connection() {
+        let resolution = USizeVec2::new(5, 5);
+        let regions = find_regions(resolution, |y, x| y == x, 0, &mut Vec::new());
+
+        assert_eq!(
+            regions,
+            vec![Region::new(USizeVec2::ZERO, USizeVec2::new(5, 5))]
+        );
+    }
+
+    #[test]
+    fn test_merge_regions_split_component() {
+        // Component that has been split at x = 4, like when labeling the mask in tiles
+        let regions = merge_regions(
+            vec![
+                Region::new(USizeVec2::new(10, 2), USizeVec2::new(12, 3)),
+                Region::new(USizeVec2::new(1, 2), USizeVec2::new(4, 5)),
+                Region::new(USizeVec2::new(4, 4), USizeVec2::new(8, 6)),
+            ],
+            1,
+        );
+
+        assert_eq!(regions.len(), 2);
+        assert!(regions.contains(&Region::new(USizeVec2::new(1, 2), USizeVec2::new(8, 6))));
+        assert!(regions.contains(&Region::new(USizeVec2::new(10, 2), USizeVec2::new(12, 3))));
+    }
+
+    #[test]
+    fn test_region_intersection() {
+        let region = Region::new(USizeVec2::new(2, 3), USizeVec2::new(8, 6));
+        assert_eq!(
+            region.intersection(&Region::new(USizeVec2::new(5, 0), USizeVec2::new(20, 4))),
+            Region::new(USizeVec2::new(5, 3), USizeVec2::new(8, 4))
+        );
+        assert_eq!(
+            region.intersection(&Region::new(USizeVec2::new(9, 0), USizeVec2::new(20, 4))),
+            Region::new(USizeVec2::new(9, 3), USizeVec2::new(9, 4))
+        );
+    }
+
+    #[test]
+    fn test_find_regions_empty() {
+        let resolution = USizeVec2::new(5, 5);
+        assert!(find_regions(resolution, |_, _| false, 1, &mut Vec::new()).is_empty());
+    }
+}
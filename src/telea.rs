@@ -9,33 +9,175 @@
 /// Implementation details about telea's algorithm can be found at
 /// https://www.olivier-augereau.com/docs/2004JGraphToolsTelea.pdf and
 /// https://webspace.science.uu.nl/~telea001/Shapes/Inpainting
+use crate::conversion::{OutputConversion, value_range};
+use crate::distance::euclidean_distance_transform;
 use crate::error::{Error, Result};
-use core::cmp::Ordering;
-use core::cmp::Reverse;
+use crate::queue::{NarrowBand, QueueItem};
+use crate::region::{Region, find_regions};
 use core::f32;
-use glam::{IVec2, USizeVec2, Vec2, Vec4};
-use ndarray::{Array1, Array2, Array3, ArrayView2, ArrayViewMut3, arr1, s};
+use core::ops::ControlFlow;
+use glam::{IVec2, USizeVec2, Vec2};
+use ndarray::{Array3, ArrayView2, ArrayView3, ArrayViewMut2, ArrayViewMut3, Zip, s};
 use num_traits::AsPrimitive;
 #[cfg(not(feature = "libm"))]
 use num_traits::Float;
-#[cfg(feature = "std")]
-use std::collections::BinaryHeap;
+#[cfg(feature = "simd")]
+use wide::f32x4;
 
 #[cfg(not(feature = "std"))]
 extern crate alloc;
 #[cfg(not(feature = "std"))]
-use alloc::{collections::BinaryHeap, vec, vec::Vec};
+use alloc::vec::Vec;
 
 /// Just a simple alias to the Array type
-type Image<P> = Array3<P>;
+type Image<'a, P> = ArrayViewMut3<'a, P>;
 /// Array containing pixel state flags
-type FlagArray = Array2<Flag>;
+type FlagArray<'a> = ArrayViewMut2<'a, Flag>;
 /// Array containing distance to mask
-type DistanceArray = Array2<f32>;
+type DistanceArray<'a> = ArrayViewMut2<'a, f32>;
 
 /// Max value as described in paper
 const MAX: f32 = 1.0e6;
 
+/// Offsets of the horizontal and vertical neighbors
+const FOUR_NEIGHBORS: [IVec2; 4] = [
+    IVec2::new(0, -1),
+    IVec2::new(-1, 0),
+    IVec2::new(0, 1),
+    IVec2::new(1, 0),
+];
+
+/// Offsets of the horizontal, vertical and diagonal neighbors
+const EIGHT_NEIGHBORS: [IVec2; 8] = [
+    IVec2::new(0, -1),
+    IVec2::new(-1, 0),
+    IVec2::new(0, 1),
+    IVec2::new(1, 0),
+    IVec2::new(-1, -1),
+    IVec2::new(1, -1),
+    IVec2::new(-1, 1),
+    IVec2::new(1, 1),
+];
+
+/// Pairs of neighbors on the horizontal and vertical axis, which are solved for the eikonal
+const AXIS_QUADRANTS: [(IVec2, IVec2); 4] = [
+    (IVec2::new(0, -1), IVec2::new(-1, 0)),
+    (IVec2::new(0, 1), IVec2::new(1, 0)),
+    (IVec2::new(0, -1), IVec2::new(1, 0)),
+    (IVec2::new(0, 1), IVec2::new(-1, 0)),
+];
+
+/// Pairs of neighbors on both diagonal axes, which are solved for the eikonal
+const DIAGONAL_QUADRANTS: [(IVec2, IVec2); 4] = [
+    (IVec2::new(-1, -1), IVec2::new(1, -1)),
+    (IVec2::new(1, 1), IVec2::new(-1, 1)),
+    (IVec2::new(-1, -1), IVec2::new(-1, 1)),
+    (IVec2::new(1, 1), IVec2::new(1, -1)),
+];
+
+/// Grid spacing between diagonal neighbors
+const DIAGONAL_SPACING: f32 = core::f32::consts::SQRT_2;
+
+/// Amount of inpainted pixels after which the progress is reported
+const PROGRESS_INTERVAL: usize = 256;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// Method used to compute the distance of pixels to the boundary of the mask.
+///
+/// The distances define the order in which the pixels are inpainted.
+pub enum DistanceMethod {
+    /// Solve the eikonal equation while marching, as described in the paper.
+    ///
+    /// This is a first order approximation, so diagonal fills can look different from
+    /// axis aligned fills.
+    #[default]
+    FastMarching,
+    /// Exact Euclidean distance transform, which makes the fill ordering isotropic.
+    Euclidean,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// Neighborhood that is used to march the narrow band into the mask.
+pub enum Connectivity {
+    /// Only the horizontal and vertical neighbors, as described in the paper.
+    #[default]
+    Four,
+    /// Horizontal, vertical and diagonal neighbors.
+    ///
+    /// The eikonal equation is then also solved along the diagonals, so diagonal structures
+    /// propagate more accurately.
+    Eight,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// Order of the upwind scheme that is used to solve the eikonal equation.
+pub enum EikonalOrder {
+    /// First order upwind scheme, as described in the paper.
+    #[default]
+    First,
+    /// Second order upwind scheme, which is used whenever two known pixels are available
+    /// in the upwind direction. Falls back to first order otherwise.
+    Second,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// Structure that stores the pixels of the narrow band while marching.
+pub enum NarrowBandQueue {
+    /// Bucket queue keyed by the quantized distance, which pushes in constant time and
+    /// only sorts the small bucket that is popped from.
+    #[default]
+    Buckets,
+    /// Binary heap, which is logarithmic for every push and pop.
+    BinaryHeap,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Options to configure the Telea algorithm.
+///
+/// ### Example
+/// ```rust
+/// use inpaint::{DistanceMethod, TeleaOptions};
+///
+/// let options = TeleaOptions {
+///     distance_method: DistanceMethod::Euclidean,
+///     ..TeleaOptions::new(5)
+/// };
+/// ```
+pub struct TeleaOptions {
+    /// Radius of near pixels that are considered for inpainting.
+    pub radius: i32,
+    /// Method used to compute the distances to the mask boundary.
+    pub distance_method: DistanceMethod,
+    /// Neighborhood that is used for marching.
+    pub connectivity: Connectivity,
+    /// Order of the scheme used to solve the eikonal equation while marching.
+    pub eikonal_order: EikonalOrder,
+    /// Structure that stores the pixels of the narrow band.
+    pub narrow_band: NarrowBandQueue,
+}
+
+impl TeleaOptions {
+    /// Initialize options with the provided radius and defaults for everything else.
+    pub fn new(radius: i32) -> Self {
+        Self {
+            radius,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for TeleaOptions {
+    fn default() -> Self {
+        Self {
+            radius: 5,
+            distance_method: DistanceMethod::default(),
+            connectivity: Connectivity::default(),
+            eikonal_order: EikonalOrder::default(),
+            narrow_band: NarrowBandQueue::default(),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 /// Flags used to define a pixel state.
 enum Flag {
@@ -65,57 +207,6 @@ impl Flag {
     }
 }
 
-#[derive(Debug, Clone)]
-/// Item for in the NarrowBand.
-///
-/// It has a priority assigned which is the most important.
-/// After that the y value is used for weight and then the x value.
-struct QueueItem {
-    pub priority: f32,
-    pub coordinates: USizeVec2,
-}
-
-impl QueueItem {
-    /// Initialize item from
-    pub fn new(cost: f32, coordinates: USizeVec2) -> Self {
-        Self {
-            priority: cost,
-            coordinates,
-        }
-    }
-}
-
-impl Ord for QueueItem {
-    fn cmp(&self, other: &Self) -> Ordering {
-        let cost_ordering = self
-            .priority
-            .partial_cmp(&other.priority)
-            .unwrap_or(Ordering::Equal);
-
-        match cost_ordering {
-            Ordering::Equal => match self.coordinates.y.cmp(&other.coordinates.y) {
-                Ordering::Equal => self.coordinates.x.cmp(&other.coordinates.x),
-                ordering => ordering,
-            },
-            _ => cost_ordering,
-        }
-    }
-}
-
-impl PartialOrd for QueueItem {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
-impl PartialEq for QueueItem {
-    fn eq(&self, other: &Self) -> bool {
-        self.priority == other.priority
-    }
-}
-
-impl Eq for QueueItem {}
-
 #[cfg(not(feature = "libm"))]
 fn powi(value: f32, pow: i32) -> f32 {
     value.powi(pow)
@@ -139,6 +230,7 @@ fn sqrt(value: f32) -> f32 {
 fn solve_eikonal(
     a: IVec2,
     b: IVec2,
+    spacing: f32,
     resolution: USizeVec2,
     distances: &DistanceArray,
     flags: &FlagArray,
@@ -163,7 +255,7 @@ fn solve_eikonal(
     let b_distance = distances[[b_usize.y, b_usize.x]];
 
     if a_flags == Flag::Known && b_flags == Flag::Known {
-        let distance = 2.0 - powi(a_distance - b_distance, 2);
+        let distance = 2.0 * spacing * spacing - powi(a_distance - b_distance, 2);
         if distance > 0.0 {
             let r = sqrt(distance);
             let mut s = (a_distance + b_distance - r) / 2.0;
@@ -179,14 +271,83 @@ fn solve_eikonal(
     };
 
     if a_flags == Flag::Known {
-        return 1.0 + a_distance;
+        return spacing + a_distance;
     }
     if b_flags == Flag::Known {
-        return 1.0 + b_distance;
+        return spacing + b_distance;
     }
     MAX
 }
 
+/// Get the upwind term of a known neighbor in the provided direction.
+///
+/// Returns the coefficient and value of the term, which is second order when the pixel
+/// after the neighbor is known as well and not further away from the boundary.
+fn upwind_term(
+    coordinates: IVec2,
+    step: IVec2,
+    resolution: USizeVec2,
+    distances: &DistanceArray,
+    flags: &FlagArray,
+) -> Option<(f32, f32)> {
+    let is_known = |position: IVec2| {
+        position.x >= 0
+            && position.y >= 0
+            && position.x < resolution.x as i32
+            && position.y < resolution.y as i32
+            && flags[[position.y as usize, position.x as usize]] == Flag::Known
+    };
+
+    let first = coordinates + step;
+    if !is_known(first) {
+        return None;
+    }
+    let first_distance = distances[[first.y as usize, first.x as usize]];
+
+    let second = first + step;
+    if is_known(second) {
+        let second_distance = distances[[second.y as usize, second.x as usize]];
+        if second_distance <= first_distance {
+            return Some((2.25, (4.0 * first_distance - second_distance) / 3.0));
+        }
+    }
+    Some((1.0, first_distance))
+}
+
+/// Solve the eikonal equation with the second order upwind scheme
+fn solve_eikonal_second_order(
+    coordinates: IVec2,
+    a_step: IVec2,
+    b_step: IVec2,
+    spacing: f32,
+    resolution: USizeVec2,
+    distances: &DistanceArray,
+    flags: &FlagArray,
+) -> f32 {
+    let solve_single = |(coefficient, value): (f32, f32)| value + spacing / sqrt(coefficient);
+    let a = upwind_term(coordinates, a_step, resolution, distances, flags);
+    let b = upwind_term(coordinates, b_step, resolution, distances, flags);
+
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            let quadratic = a.0 + b.0;
+            let linear = -2.0 * (a.0 * a.1 + b.0 * b.1);
+            let constant = a.0 * powi(a.1, 2) + b.0 * powi(b.1, 2) - spacing * spacing;
+            let discriminant = powi(linear, 2) - 4.0 * quadratic * constant;
+            if discriminant >= 0.0 {
+                let s = (-linear + sqrt(discriminant)) / (2.0 * quadratic);
+                if s >= a.1 && s >= b.1 {
+                    return s;
+                }
+            }
+            solve_single(a).min(solve_single(b))
+        }
+        (Some(a), None) => solve_single(a),
+        (None, Some(b)) => solve_single(b),
+        (None, None) => MAX,
+    }
+}
+
 /// Compute gradient weighting for both x and y
 fn pixel_gradient(
     coordinates: USizeVec2,
@@ -246,92 +407,132 @@ fn normalize_value<P>(value: P) -> f32
 where
     P: AsPrimitive<f32>,
 {
-    value.as_()
-        / match core::any::TypeId::of::<P>() {
-            id if id == core::any::TypeId::of::<u8>() => u8::MAX as f32,
-            id if id == core::any::TypeId::of::<u16>() => u16::MAX as f32,
-            id if id == core::any::TypeId::of::<u32>() => u32::MAX as f32,
-            id if id == core::any::TypeId::of::<u32>() => u64::MAX as f32,
-            id if id == core::any::TypeId::of::<u32>() => u128::MAX as f32,
-            id if id == core::any::TypeId::of::<i8>() => i8::MAX as f32,
-            id if id == core::any::TypeId::of::<i16>() => i16::MAX as f32,
-            id if id == core::any::TypeId::of::<i32>() => i32::MAX as f32,
-            id if id == core::any::TypeId::of::<i32>() => i64::MAX as f32,
-            id if id == core::any::TypeId::of::<i32>() => i128::MAX as f32,
-            _ => 1.0,
-        }
-}
-
-/// Convert the input array of any type to the FlagArray (which consists of enum values)
-fn convert_mask_to_flag_array<P>(mask: &ArrayView2<P>, resolution: USizeVec2) -> FlagArray
+    value.as_() / value_range::<P>().1
+}
+
+/// Convert a single mask value to its flag
+fn mask_value_to_flag<P>(value: P) -> Flag
+where
+    P: AsPrimitive<f32>,
+{
+    let value: f32 = normalize_value(value).ceil();
+    Flag::from_value(value as u8)
+}
+
+/// Check if the mask value marks the pixel to be inpainted
+pub(crate) fn is_masked<P>(value: P) -> bool
 where
     P: AsPrimitive<f32>,
 {
-    FlagArray::from_shape_fn((resolution.y, resolution.x), |(y, x)| {
-        let value: f32 = normalize_value(mask[[y, x]]).ceil();
-        Flag::from_value(value as u8)
-    })
+    mask_value_to_flag(value) == Flag::Band
 }
 
 /// Get the coordinates around the specified coordinate
-fn get_neighbors(coordinates: IVec2) -> [IVec2; 4] {
-    [
-        coordinates + IVec2::new(0, -1),
-        coordinates + IVec2::new(-1, 0),
-        coordinates + IVec2::new(0, 1),
-        coordinates + IVec2::new(1, 0),
-    ]
+fn get_neighbors(coordinates: IVec2, connectivity: Connectivity) -> impl Iterator<Item = IVec2> {
+    let offsets: &'static [IVec2] = match connectivity {
+        Connectivity::Four => &FOUR_NEIGHBORS,
+        Connectivity::Eight => &EIGHT_NEIGHBORS,
+    };
+    offsets.iter().map(move |offset| coordinates + *offset)
 }
 
 /// Calculate the distances between mask edges and pixels outside of mask area
+///
+/// The `inner_flags` and `current_heap` are buffers that are used while marching outwards.
 fn compute_outside_distances(
     resolution: USizeVec2,
     distances: &mut DistanceArray,
     flags: &FlagArray,
-    heap: &BinaryHeap<Reverse<QueueItem>>,
-    radius: i32,
+    heap: &NarrowBand,
+    options: &TeleaOptions,
+    inner_flags: &mut FlagArray,
+    current_heap: &mut NarrowBand,
 ) -> Result<()> {
-    let mut inner_flags = flags.clone().mapv(|f| f.flip());
-    let mut current_heap = heap.clone();
+    inner_flags.zip_mut_with(flags, |inner_flag, flag| *inner_flag = flag.flip());
+    current_heap.copy_from(heap);
 
     let mut last_distance = 0.0;
-    let double_radius = radius as f32 * 2.0;
+    let double_radius = options.radius as f32 * 2.0;
     while !current_heap.is_empty() {
         if last_distance >= double_radius {
             break;
         };
 
         let coordinates = if let Some(node) = current_heap.pop() {
-            node.0.coordinates
+            node.coordinates
         } else {
             break;
         };
         inner_flags[[coordinates.y, coordinates.x]] = Flag::Known;
 
-        let neighbors = get_neighbors(coordinates.as_ivec2());
+        let neighbors = get_neighbors(coordinates.as_ivec2(), options.connectivity);
         for neighbor in neighbors {
-            last_distance = match get_eikonal(resolution, distances, &mut inner_flags, neighbor) {
+            last_distance = match get_eikonal(resolution, distances, inner_flags, neighbor, options)
+            {
                 Some(value) => value,
                 None => continue,
             };
             distances[[neighbor.y as usize, neighbor.x as usize]] = last_distance;
             inner_flags[[neighbor.y as usize, neighbor.x as usize]] = Flag::Band;
-            current_heap.push(Reverse(QueueItem::new(
-                last_distance,
-                neighbor.as_usizevec2(),
-            )));
+            current_heap.push(QueueItem::new(last_distance, neighbor.as_usizevec2()));
         }
     }
-    *distances *= -1.0;
+    distances.mapv_inplace(|distance| -distance);
     Ok(())
 }
 
+/// Calculate the exact Euclidean distances of all pixels to the narrow band.
+///
+/// Pixels outside the mask get a negative distance up until twice the radius, like
+/// `compute_outside_distances` does. Pixels inside the mask get their positive distance,
+/// which is picked up while marching.
+fn compute_euclidean_distances(
+    resolution: USizeVec2,
+    distances: &mut DistanceArray,
+    flags: &FlagArray,
+    radius: i32,
+) {
+    let euclidean = euclidean_distance_transform(resolution, |y, x| flags[[y, x]] == Flag::Band);
+    let double_radius = radius as f32 * 2.0;
+    ndarray::Zip::from(distances)
+        .and(&euclidean)
+        .and(flags)
+        .for_each(|distance, &euclidean_distance, flag| {
+            *distance = match flag {
+                Flag::Inside => euclidean_distance,
+                _ if euclidean_distance > double_radius => -MAX,
+                _ => -euclidean_distance,
+            }
+        });
+}
+
+/// Get the precomputed Euclidean distance of a pixel that is still inside the mask
+fn get_euclidean(
+    resolution: USizeVec2,
+    distances: &DistanceArray,
+    flags: &FlagArray,
+    neighbor: IVec2,
+) -> Option<f32> {
+    if neighbor.y < 0
+        || neighbor.y >= resolution.y as i32
+        || neighbor.x < 0
+        || neighbor.x >= resolution.x as i32
+    {
+        return None;
+    }
+    if flags[[neighbor.y as usize, neighbor.x as usize]] != Flag::Inside {
+        return None;
+    }
+    Some(distances[[neighbor.y as usize, neighbor.x as usize]])
+}
+
 /// Solve the eikonal equations to find the distance to the boundary
 fn get_eikonal(
     resolution: USizeVec2,
     distances: &mut DistanceArray,
     flags: &mut FlagArray,
     neighbor: IVec2,
+    options: &TeleaOptions,
 ) -> Option<f32> {
     if neighbor.y < 0
         || neighbor.y >= resolution.y as i32
@@ -343,269 +544,1246 @@ fn get_eikonal(
     if flags[[neighbor.y as usize, neighbor.x as usize]] != Flag::Inside {
         return None;
     }
-    let eikonals = vec![
-        solve_eikonal(
-            neighbor + IVec2::new(0, -1),
-            neighbor + IVec2::new(-1, 0),
-            resolution,
-            distances,
-            flags,
-        ),
-        solve_eikonal(
-            neighbor + IVec2::new(0, 1),
-            neighbor + IVec2::new(1, 0),
-            resolution,
-            distances,
-            flags,
-        ),
-        solve_eikonal(
-            neighbor + IVec2::new(0, -1),
-            neighbor + IVec2::new(1, 0),
+    let solve = |(a_step, b_step): &(IVec2, IVec2), spacing: f32| match options.eikonal_order {
+        EikonalOrder::First => solve_eikonal(
+            neighbor + *a_step,
+            neighbor + *b_step,
+            spacing,
             resolution,
             distances,
             flags,
         ),
-        solve_eikonal(
-            neighbor + IVec2::new(0, 1),
-            neighbor + IVec2::new(-1, 0),
-            resolution,
-            distances,
-            flags,
+        EikonalOrder::Second => solve_eikonal_second_order(
+            neighbor, *a_step, *b_step, spacing, resolution, distances, flags,
         ),
-    ];
-    Some(Vec4::from_slice(&eikonals).min_element())
+    };
+
+    let mut distance = AXIS_QUADRANTS
+        .iter()
+        .map(|quadrant| solve(quadrant, 1.0))
+        .fold(MAX, f32::min);
+    if options.connectivity == Connectivity::Eight {
+        distance = DIAGONAL_QUADRANTS
+            .iter()
+            .map(|quadrant| solve(quadrant, DIAGONAL_SPACING))
+            .fold(distance, f32::min);
+    }
+    Some(distance)
 }
 
-fn inpaint_pixel(
-    image: &Image<f32>,
-    coordinate: USizeVec2,
-    resolution: USizeVec2,
-    distances: &mut DistanceArray,
-    flags: &mut FlagArray,
-    radius: i32,
-) -> Array1<f32> {
-    let distance = distances[[coordinate.y, coordinate.x]];
-    let gradient_distance = pixel_gradient(coordinate, resolution, distances, flags);
+#[derive(Clone, Copy, Debug)]
+/// Precomputed neighbor within the radius of the pixel that is inpainted
+struct KernelItem {
+    /// Offset of the neighbor to the inpainted pixel
+    offset: IVec2,
+    /// Offset of the neighbor in the flattened arrays
+    index_offset: isize,
+    /// Direction from the neighbor to the inpainted pixel
+    direction: Vec2,
+    /// Weighting by the distance between the neighbor and inpainted pixel
+    distance_factor: f32,
+}
 
-    let mut weight_sum = 0.0;
-    let channels = image.dim().2;
-    let mut output_pixel = arr1(&vec![0.0; channels]);
+#[derive(Clone, Debug, Default)]
+/// Precomputed neighbors within the radius, in the order they are accumulated
+struct Kernel {
+    radius: usize,
+    items: Vec<KernelItem>,
+}
+
+impl Kernel {
+    /// Recompute the neighbors for the radius and width of the flattened arrays
+    fn update(&mut self, radius: i32, width: usize) {
+        self.radius = radius.max(0) as usize;
+        compute_kernel(radius, width, &mut self.items);
+    }
+}
+
+/// Precompute all neighbors within the radius, in the order they are accumulated
+fn compute_kernel(radius: i32, width: usize, kernel: &mut Vec<KernelItem>) {
+    kernel.clear();
     for y in -radius..=radius {
         for x in -radius..=radius {
-            let current_coordinate = coordinate.as_ivec2() + IVec2::new(x, y);
-            if current_coordinate.y < 0
-                || current_coordinate.y >= resolution.y as i32
-                || current_coordinate.x < 0
-                || current_coordinate.x >= resolution.x as i32
-            {
+            // The inpainted pixel itself is always inside the mask
+            if x == 0 && y == 0 {
                 continue;
             }
-            let neighbor = current_coordinate.as_usizevec2();
-            if flags[[neighbor.y, neighbor.x]] == Flag::Inside {
-                continue;
-            }
-            let direction = coordinate.as_ivec2() - neighbor.as_ivec2();
+            let direction = IVec2::new(-x, -y);
             let length_pow = powi(direction.x as f32, 2) + powi(direction.y as f32, 2);
             let length = sqrt(length_pow);
             if length > radius as f32 {
                 continue;
             }
+            kernel.push(KernelItem {
+                offset: IVec2::new(x, y),
+                index_offset: y as isize * width as isize + x as isize,
+                direction: direction.as_vec2(),
+                distance_factor: 1.0 / (length * length_pow),
+            });
+        }
+    }
+}
 
-            let mut direction_factor = (direction.y as f32 * gradient_distance.y
-                + direction.x as f32 * gradient_distance.x)
-                .abs();
-            if direction_factor == 0.0 {
-                direction_factor = f32::EPSILON;
-            }
+/// Compute the weight of a known neighbor for the inpainted pixel
+fn neighbor_weight(
+    item: &KernelItem,
+    gradient_distance: Vec2,
+    distance: f32,
+    neighbor_distance: f32,
+) -> f32 {
+    let mut direction_factor =
+        (item.direction.y * gradient_distance.y + item.direction.x * gradient_distance.x).abs();
+    if direction_factor == 0.0 {
+        direction_factor = f32::EPSILON;
+    }
 
-            let neighbor_distance = distances[[neighbor.y, neighbor.x]];
-            let level_factor = 1.0 / (1.0 + (neighbor_distance - distance).abs());
-            let distance_factor = 1.0 / (length * length_pow);
-            let weight = (direction_factor * distance_factor * level_factor).abs();
-            for (channel, value) in output_pixel.iter_mut().enumerate() {
-                *value += weight
-                    * image[[
-                        current_coordinate.y as usize,
-                        current_coordinate.x as usize,
-                        channel,
-                    ]];
-            }
-            weight_sum += weight;
+    let level_factor = 1.0 / (1.0 + (neighbor_distance - distance).abs());
+    (direction_factor * item.distance_factor * level_factor).abs()
+}
+
+/// Accumulate the weighted neighbors of the flattened image into the output pixel.
+///
+/// Returns the sum of the weights.
+fn accumulate_pixels(
+    image: &[f32],
+    neighbors: impl Iterator<Item = (usize, f32)>,
+    output_pixel: &mut [f32],
+) -> f32 {
+    let channels = output_pixel.len();
+    let mut weight_sum = 0.0;
+    for (index, weight) in neighbors {
+        let pixel = &image[index * channels..(index + 1) * channels];
+        for (value, channel) in output_pixel.iter_mut().zip(pixel) {
+            *value += weight * channel;
         }
+        weight_sum += weight;
     }
-    for channel in output_pixel.iter_mut() {
-        *channel /= weight_sum;
+    weight_sum
+}
+
+/// Accumulate the weighted neighbors of a single channel image, keeping the sum in a register
+fn accumulate_single_channel(
+    image: &[f32],
+    neighbors: impl Iterator<Item = (usize, f32)>,
+    output_pixel: &mut [f32],
+) -> f32 {
+    let mut value = 0.0;
+    let mut weight_sum = 0.0;
+    for (index, weight) in neighbors {
+        value += weight * image[index];
+        weight_sum += weight;
     }
-    output_pixel
+    output_pixel[0] = value;
+    weight_sum
 }
 
-/// Data structure that stores the processing data.
-struct ProcessData {
-    distances: DistanceArray,
-    process_image: Image<f32>,
-    flags: FlagArray,
-    heap: BinaryHeap<Reverse<QueueItem>>,
+#[cfg(feature = "simd")]
+/// Accumulate whole pixels of up to four channels at once in a SIMD vector.
+///
+/// Every lane does the same multiplication and addition as [`accumulate_pixels`],
+/// so the results are identical. Pixels with less than four channels are loaded together
+/// with the start of the next pixel, which only ends up in the unused lanes.
+/// Only the last pixel of the image is padded, as there is no next pixel to load.
+fn accumulate_pixels_simd<const CHANNELS: usize>(
+    image: &[f32],
+    neighbors: impl Iterator<Item = (usize, f32)>,
+    output_pixel: &mut [f32],
+) -> f32 {
+    let mut value = f32x4::ZERO;
+    let mut weight_sum = 0.0;
+    for (index, weight) in neighbors {
+        let start = index * CHANNELS;
+        let pixel = match image[start..].first_chunk::<4>() {
+            Some(lanes) => f32x4::from(*lanes),
+            None => {
+                let mut lanes = [0.0; 4];
+                lanes[..CHANNELS].copy_from_slice(&image[start..start + CHANNELS]);
+                f32x4::from(lanes)
+            }
+        };
+        value += f32x4::splat(weight) * pixel;
+        weight_sum += weight;
+    }
+    output_pixel.copy_from_slice(&value.to_array()[..CHANNELS]);
+    weight_sum
 }
 
-impl ProcessData {
-    /// Initialize the process data and precompute the distances, flags and fill heap
-    pub fn new<ImageType, MaskType>(
-        resolution: USizeVec2,
-        image: &ArrayViewMut3<ImageType>,
-        mask: &ArrayView2<MaskType>,
-        radius: i32,
-    ) -> Result<Self>
-    where
-        ImageType: AsPrimitive<f32> + Copy,
-        MaskType: AsPrimitive<f32> + Copy + 'static,
-    {
-        let mut distances = Array2::<f32>::from_elem((resolution.y, resolution.x), MAX);
-        let process_image: Image<f32> = image.mapv(|pixel| pixel.as_());
-        let mask_array = convert_mask_to_flag_array(mask, resolution);
-        let mut flags = mask_array
-            .to_owned()
-            .mapv(|f| if f == Flag::Band { Flag::Inside } else { f });
-        let mut heap = BinaryHeap::new();
-        let non_zero: Vec<_> = flags
-            .indexed_iter()
-            .filter_map(|(index, &item)| {
-                if item != Flag::Known {
-                    Some(index)
-                } else {
-                    None
-                }
-            })
-            .collect();
+/// Inpaint a single pixel from the known pixels within the radius into the output pixel.
+///
+/// When the full kernel fits within the image, the flattened arrays are accessed directly
+/// without checking the bounds for every neighbor.
+///
+/// Returns false when no known pixel contributed to the pixel, in which case the output
+/// pixel doesn't contain a valid value.
+fn inpaint_pixel(
+    image: &Image<f32>,
+    coordinate: USizeVec2,
+    resolution: USizeVec2,
+    distances: &DistanceArray,
+    flags: &FlagArray,
+    kernel: &Kernel,
+    output_pixel: &mut [f32],
+) -> bool {
+    let distance = distances[[coordinate.y, coordinate.x]];
+    let gradient_distance = pixel_gradient(coordinate, resolution, distances, flags);
 
-        for index in non_zero.iter() {
-            let coordinates = USizeVec2::new(index.1, index.0);
-            let neighbors = get_neighbors(coordinates.as_ivec2());
-            for neighbor in neighbors {
-                if neighbor.y < 0
-                    || neighbor.y >= resolution.y as i32
-                    || neighbor.x < 0
-                    || neighbor.x >= resolution.x as i32
+    let mut weight_sum = 0.0;
+    output_pixel.fill(0.0);
+    let channels = output_pixel.len();
+    let radius = kernel.radius;
+    let is_interior = coordinate.x >= radius
+        && coordinate.y >= radius
+        && coordinate.x + radius < resolution.x
+        && coordinate.y + radius < resolution.y;
+
+    match (
+        is_interior,
+        image.as_slice(),
+        distances.as_slice(),
+        flags.as_slice(),
+    ) {
+        (true, Some(image), Some(distances), Some(flags)) => {
+            let center = (coordinate.y * resolution.x + coordinate.x) as isize;
+            let neighbors = kernel.items.iter().filter_map(|item| {
+                let index = (center + item.index_offset) as usize;
+                if flags[index] == Flag::Inside {
+                    return None;
+                }
+                let weight = neighbor_weight(item, gradient_distance, distance, distances[index]);
+                Some((index, weight))
+            });
+            weight_sum = match channels {
+                1 => accumulate_single_channel(image, neighbors, output_pixel),
+                #[cfg(feature = "simd")]
+                3 => accumulate_pixels_simd::<3>(image, neighbors, output_pixel),
+                #[cfg(feature = "simd")]
+                4 => accumulate_pixels_simd::<4>(image, neighbors, output_pixel),
+                _ => accumulate_pixels(image, neighbors, output_pixel),
+            };
+        }
+        _ => {
+            for item in &kernel.items {
+                let current_coordinate = coordinate.as_ivec2() + item.offset;
+                if current_coordinate.y < 0
+                    || current_coordinate.y >= resolution.y as i32
+                    || current_coordinate.x < 0
+                    || current_coordinate.x >= resolution.x as i32
                 {
                     continue;
-                };
-                if flags[[neighbor.y as usize, neighbor.x as usize]] == Flag::Band {
+                }
+                let neighbor = current_coordinate.as_usizevec2();
+                if flags[[neighbor.y, neighbor.x]] == Flag::Inside {
                     continue;
                 }
-
-                if mask_array[[neighbor.y as usize, neighbor.x as usize]] == Flag::Known {
-                    flags[[neighbor.y as usize, neighbor.x as usize]] = Flag::Band;
-                    distances[[neighbor.y as usize, neighbor.x as usize]] = 0.0;
-                    heap.push(Reverse(QueueItem::new(0.0, neighbor.as_usizevec2())));
+                let weight = neighbor_weight(
+                    item,
+                    gradient_distance,
+                    distance,
+                    distances[[neighbor.y, neighbor.x]],
+                );
+                for (channel, value) in output_pixel.iter_mut().enumerate() {
+                    *value += weight * image[[neighbor.y, neighbor.x, channel]];
                 }
+                weight_sum += weight;
             }
         }
-
-        compute_outside_distances(resolution, &mut distances, &flags, &heap, radius)?;
-
-        Ok(Self {
-            distances,
-            process_image,
-            flags,
-            heap,
-        })
     }
+    if weight_sum.is_nan() || weight_sum <= 0.0 {
+        return false;
+    }
+    for channel in output_pixel.iter_mut() {
+        *channel /= weight_sum;
+    }
+    true
 }
 
-/// ## Inpaint the input image according to the mask provided.
-///
-/// 3d arrays are expected for inpainting of the image, while 2d array is expected for mask.
-/// As the mask consists of only one mask channel.
-///
-/// In the image array, the rows is the height, the columns is the width
-/// and the dimensions are the channels.
-///
-/// ### Arguments:
+/// Reusable buffers for the Telea algorithm.
 ///
-/// * `image`: array to inpaint.
-/// * `mask`: mask that defines the region that will be inpainted
-/// * `radius`: radius of near pixels that are considered for npainting.
+/// Every call of [`telea_inpaint`] allocates the buffers that are needed for processing.
+/// When inpainting many images, like the frames of a video, the workspace can be reused
+/// so the buffers are only allocated once and grown when needed.
+/// With the `rayon` feature, the regions are processed by the calling thread together with
+/// the current rayon pool, so no threads are started for any call either. Every thread
+/// keeps a single workspace, so only the crops that are being processed are in memory.
 ///
 /// ### Example
 /// ```rust
-/// use inpaint::telea_inpaint;
+/// use inpaint::{TeleaOptions, TeleaWorkspace};
 /// use ndarray::{Array2, Array3};
-/// use glam::USizeVec2;
 ///
-/// let resolution = USizeVec2::new(1920, 1080);
-/// // obviously you need to use actual data, this is just an example
-/// let mut input_image = Array3::from_elem((resolution.y, resolution.x, 4), 0.0);
-/// let mask = Array2::from_elem((resolution.y, resolution.x), 0.0);
+/// let mut workspace = TeleaWorkspace::new();
+/// let options = TeleaOptions::new(5);
+/// let mask = Array2::from_elem((1080, 1920), 0.0);
 ///
-/// telea_inpaint(&mut input_image.view_mut(), &mask.view(), 1).unwrap();
+/// for _ in 0..3 {
+///     let mut frame = Array3::from_elem((1080, 1920, 3), 0.0);
+///     workspace
+///         .telea_inpaint(&mut frame.view_mut(), &mask.view(), &options)
+///         .unwrap();
+/// }
 /// ```
-pub fn telea_inpaint<ImageType, MaskType>(
-    image: &mut ArrayViewMut3<ImageType>,
-    mask: &ArrayView2<MaskType>,
-    radius: i32,
-) -> Result<()>
-where
-    ImageType: AsPrimitive<f32> + Copy,
-    f32: num_traits::AsPrimitive<ImageType>,
-    MaskType: AsPrimitive<f32> + Copy + 'static,
-{
-    if image.shape()[1] != mask.ncols() || image.shape()[0] != mask.nrows() {
-        return Err(Error::DimensionMismatch);
-    }
+#[derive(Debug, Default)]
+pub struct TeleaWorkspace {
+    process_image: Vec<f32>,
+    mask: Vec<Flag>,
+    distances: Vec<f32>,
+    flags: Vec<Flag>,
+    outside_flags: Vec<Flag>,
+    heap: NarrowBand,
+    outside_heap: NarrowBand,
+    pixel: Vec<f32>,
+    #[cfg(not(feature = "rayon"))]
+    inpainted: Vec<f32>,
+    kernel: Kernel,
+    pub(crate) visited: Vec<bool>,
+    /// Workspaces of the other threads that process regions
+    #[cfg(feature = "rayon")]
+    thread_workspaces: Vec<TeleaWorkspace>,
+    /// Inpainted pixels of every region, in the order of the regions
+    #[cfg(feature = "rayon")]
+    region_inpainted: Vec<Vec<f32>>,
+}
 
-    let resolution = USizeVec2::new(image.shape()[1], image.shape()[0]);
-    let mut process_data = ProcessData::new(resolution, image, mask, radius)?;
-    while !process_data.heap.is_empty() {
-        let coordinates = if let Some(node) = process_data.heap.pop() {
-            node.0.coordinates
-        } else {
-            return Err(Error::HeapDoesNotContainData);
-        };
-        process_data.flags[[coordinates.y, coordinates.x]] = Flag::Known;
+#[cfg(feature = "rayon")]
+/// Region that has been loaded into a workspace, to be inpainted by any thread
+struct RegionJob {
+    index: usize,
+    workspace: TeleaWorkspace,
+    inpainted: Vec<f32>,
+}
 
-        let neighbors = get_neighbors(coordinates.as_ivec2());
+#[cfg(feature = "rayon")]
+/// Free workspaces and results that the calling thread collects from the jobs of all threads
+struct RegionResults {
+    free: Vec<TeleaWorkspace>,
+    inpainted: Vec<Vec<f32>>,
+    error: Option<Error>,
+}
 
-        for neighbor in neighbors {
-            if neighbor.y >= resolution.y as i32 || neighbor.x >= resolution.x as i32 {
-                continue;
-            }
+#[cfg(feature = "rayon")]
+impl RegionResults {
+    /// Take back the workspace and the result of a job, returning false if it failed
+    fn finish(&mut self, (job, result): (RegionJob, Result<()>)) -> bool {
+        self.inpainted[job.index] = job.inpainted;
+        self.free.push(job.workspace);
+        self.fail(result)
+    }
 
-            let distance = match get_eikonal(
-                resolution,
-                &mut process_data.distances,
-                &mut process_data.flags,
-                neighbor,
-            ) {
-                Some(value) => value,
-                None => continue,
+    /// Keep the first error, returning false if the result is one
+    fn fail(&mut self, result: Result<()>) -> bool {
+        match result {
+            Ok(()) => true,
+            Err(failure) => {
+                self.error.get_or_insert(failure);
+                false
+            }
+        }
+    }
+}
+
+impl TeleaWorkspace {
+    /// Initialize an empty workspace, the buffers are allocated on first use.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// ## Inpaint the input image according to the mask provided, reusing the buffers.
+    ///
+    /// Same as [`telea_inpaint_with_options`], but without allocating new buffers
+    /// for every call.
+    ///
+    /// ### Arguments:
+    ///
+    /// * `image`: array to inpaint.
+    /// * `mask`: mask that defines the region that will be inpainted
+    /// * `options`: options to configure the algorithm.
+    pub fn telea_inpaint<ImageType, MaskType>(
+        &mut self,
+        image: &mut ArrayViewMut3<ImageType>,
+        mask: &ArrayView2<MaskType>,
+        options: &TeleaOptions,
+    ) -> Result<()>
+    where
+        ImageType: AsPrimitive<f32> + Copy,
+        f32: num_traits::AsPrimitive<ImageType>,
+        MaskType: AsPrimitive<f32> + Copy + 'static,
+    {
+        self.telea_inpaint_with_progress(image, mask, options, |_, _| ControlFlow::Continue(()))
+    }
+
+    /// ## Inpaint the input image according to the mask provided, while reporting the progress.
+    ///
+    /// Same as [`TeleaWorkspace::telea_inpaint`], but calls `progress` with the amount of
+    /// inpainted pixels and the total amount of masked pixels while inpainting.
+    /// When the callback returns [`ControlFlow::Break`], inpainting stops with
+    /// [`Error::Cancelled`] and the image is left untouched.
+    ///
+    /// ### Arguments:
+    ///
+    /// * `image`: array to inpaint.
+    /// * `mask`: mask that defines the region that will be inpainted
+    /// * `options`: options to configure the algorithm.
+    /// * `progress`: callback that receives the inpainted and total amount of pixels.
+    pub fn telea_inpaint_with_progress<ImageType, MaskType, Progress>(
+        &mut self,
+        image: &mut ArrayViewMut3<ImageType>,
+        mask: &ArrayView2<MaskType>,
+        options: &TeleaOptions,
+        progress: Progress,
+    ) -> Result<()>
+    where
+        ImageType: AsPrimitive<f32> + Copy,
+        f32: num_traits::AsPrimitive<ImageType>,
+        MaskType: AsPrimitive<f32> + Copy + 'static,
+        Progress: FnMut(usize, usize) -> ControlFlow<()>,
+    {
+        // Integers are inpainted in the 0-1 range, so they are rounded exactly like floats
+        let (_, max_value) = value_range::<ImageType>();
+        let crops = self.inpaint_regions(&image.view(), mask, options, max_value, progress)?;
+        let conversion = OutputConversion::between::<f32, ImageType>();
+        self.write_inpainted(image, mask, &crops, |value| conversion.convert(value));
+        Ok(())
+    }
+
+    /// ## Inpaint the input image according to the mask provided into a separate output.
+    ///
+    /// The output can be of another type than the input image. All pixels are converted
+    /// with the provided conversion, which can scale, clamp and round the values.
+    /// The input image is left untouched.
+    ///
+    /// ### Arguments:
+    ///
+    /// * `image`: array to inpaint.
+    /// * `mask`: mask that defines the region that will be inpainted
+    /// * `output`: array with the same shape as the image, that receives the result.
+    /// * `options`: options to configure the algorithm.
+    /// * `conversion`: conversion of the values from the image to the output.
+    pub fn telea_inpaint_into<InputType, OutputType, MaskType>(
+        &mut self,
+        image: &ArrayView3<InputType>,
+        mask: &ArrayView2<MaskType>,
+        output: &mut ArrayViewMut3<OutputType>,
+        options: &TeleaOptions,
+        conversion: &OutputConversion,
+    ) -> Result<()>
+    where
+        InputType: AsPrimitive<f32> + Copy,
+        OutputType: Copy + 'static,
+        f32: num_traits::AsPrimitive<OutputType>,
+        MaskType: AsPrimitive<f32> + Copy + 'static,
+    {
+        if output.shape() != image.shape() {
+            return Err(Error::DimensionMismatch);
+        }
+
+        // Integers are inpainted in the 0-1 range, like in place, so both round the same
+        let (_, max_value) = value_range::<InputType>();
+        let crops = self.inpaint_regions(image, mask, options, max_value, |_, _| {
+            ControlFlow::Continue(())
+        })?;
+        Zip::from(&mut *output)
+            .and(image)
+            .for_each(|output, input| *output = conversion.convert(input.as_()));
+        self.write_inpainted(output, mask, &crops, |value| {
+            conversion.convert(value * max_value)
+        });
+        Ok(())
+    }
+
+    /// Find and inpaint all regions of the mask, without writing them to an image yet.
+    ///
+    /// Returns the regions and their crops, which are written by [`Self::write_inpainted`].
+    /// The values of the image are divided by `max_value` while inpainting.
+    fn inpaint_regions<ImageType, MaskType, Progress>(
+        &mut self,
+        image: &ArrayView3<ImageType>,
+        mask: &ArrayView2<MaskType>,
+        options: &TeleaOptions,
+        max_value: f32,
+        mut progress: Progress,
+    ) -> Result<Vec<(Region, Region)>>
+    where
+        ImageType: AsPrimitive<f32> + Copy,
+        MaskType: AsPrimitive<f32> + Copy + 'static,
+        Progress: FnMut(usize, usize) -> ControlFlow<()>,
+    {
+        if image.shape()[1] != mask.ncols() || image.shape()[0] != mask.nrows() {
+            return Err(Error::DimensionMismatch);
+        }
+
+        let resolution = USizeVec2::new(mask.ncols(), mask.nrows());
+        let regions = find_regions(
+            resolution,
+            |y, x| is_masked(mask[[y, x]]),
+            region_separation(options),
+            &mut self.visited,
+        );
+        let total = mask.iter().filter(|value| is_masked(**value)).count();
+        let context = region_context(options);
+        let crops: Vec<_> = regions
+            .into_iter()
+            .map(|region| (region, region.expand(context, resolution)))
+            .collect();
+        if crops.is_empty() {
+            return Ok(crops);
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            let mut filled = 0;
+            self.inpainted.clear();
+            for (region, crop) in crops.iter().copied() {
+                self.load_crop(image, mask, crop, max_value);
+                let counted = Region::new(region.min - crop.min, region.max - crop.min);
+                self.process(crop, options, counted, &mut |count| {
+                    filled += count;
+                    progress(filled, total)
+                })?;
+                let mut inpainted = core::mem::take(&mut self.inpainted);
+                self.store_region(region, crop, &mut inpainted);
+                self.inpainted = inpainted;
+            }
+        }
+
+        #[cfg(feature = "rayon")]
+        {
+            use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+            use std::sync::{Mutex, PoisonError, mpsc};
+
+            let helpers = crops.len().min(rayon::current_num_threads()) - 1;
+            let mut state = RegionResults {
+                free: core::mem::take(&mut self.thread_workspaces),
+                inpainted: core::mem::take(&mut self.region_inpainted),
+                error: None,
+            };
+            if state.free.len() < helpers {
+                state.free.resize_with(helpers, TeleaWorkspace::new);
+            }
+            state.inpainted.resize_with(crops.len(), Vec::new);
+
+            let filled = AtomicUsize::new(0);
+            let cancelled = AtomicBool::new(false);
+            let run = |job: &mut RegionJob, progress: &mut dyn FnMut(usize) -> ControlFlow<()>| {
+                let (region, crop) = crops[job.index];
+                let counted = Region::new(region.min - crop.min, region.max - crop.min);
+                job.workspace.process(crop, options, counted, progress)?;
+                job.inpainted.clear();
+                job.workspace.store_region(region, crop, &mut job.inpainted);
+                Ok(())
             };
+            let (job_sender, job_receiver) = mpsc::channel::<RegionJob>();
+            let job_receiver = Mutex::new(job_receiver);
+            let (done_sender, done_receiver) = mpsc::channel::<(RegionJob, Result<()>)>();
+
+            // The calling thread processes regions as well and never waits on the pool,
+            // so inpainting from within a rayon pool can't deadlock. It loads the crops of
+            // the other threads into their free workspace, and it is the only thread that
+            // calls the progress callback, with the regions of all threads.
+            let mut reported = 0;
+            rayon::in_place_scope(|scope| {
+                for _ in 0..helpers {
+                    let (run, job_receiver, done_sender) =
+                        (&run, &job_receiver, done_sender.clone());
+                    let (filled, cancelled) = (&filled, &cancelled);
+                    scope.spawn(move |_| {
+                        loop {
+                            let job = job_receiver
+                                .lock()
+                                .unwrap_or_else(PoisonError::into_inner)
+                                .recv();
+                            let Ok(mut job) = job else {
+                                break;
+                            };
+                            let result = run(&mut job, &mut |count| {
+                                filled.fetch_add(count, Ordering::Relaxed);
+                                match cancelled.load(Ordering::Relaxed) {
+                                    true => ControlFlow::Break(()),
+                                    false => ControlFlow::Continue(()),
+                                }
+                            });
+                            if done_sender.send((job, result)).is_err() {
+                                break;
+                            }
+                        }
+                    });
+                }
+                drop(done_sender);
+
+                let mut report = |count| {
+                    reported = filled.fetch_add(count, Ordering::Relaxed) + count;
+                    match cancelled.load(Ordering::Relaxed) {
+                        true => ControlFlow::Break(()),
+                        false => progress(reported, total),
+                    }
+                };
+                let mut outstanding = 0;
+                for (index, (region, crop)) in crops.iter().copied().enumerate() {
+                    while let Ok(done) = done_receiver.try_recv() {
+                        outstanding -= 1;
+                        if !state.finish(done) {
+                            cancelled.store(true, Ordering::Relaxed);
+                        }
+                    }
+                    if cancelled.load(Ordering::Relaxed) {
+                        break;
+                    }
+
+                    if let Some(mut workspace) = state.free.pop() {
+                        workspace.load_crop(image, mask, crop, max_value);
+                        let inpainted = core::mem::take(&mut state.inpainted[index]);
+                        let job = RegionJob {
+                            index,
+                            workspace,
+                            inpainted,
+                        };
+                        match job_sender.send(job) {
+                            Ok(()) => outstanding += 1,
+                            Err(mpsc::SendError(job)) => state.free.push(job.workspace),
+                        }
+                        continue;
+                    }
+
+                    self.load_crop(image, mask, crop, max_value);
+                    let counted = Region::new(region.min - crop.min, region.max - crop.min);
+                    let result = self.process(crop, options, counted, &mut report);
+                    if result.is_ok() {
+                        state.inpainted[index].clear();
+                        self.store_region(region, crop, &mut state.inpainted[index]);
+                    }
+                    if !state.fail(result) {
+                        cancelled.store(true, Ordering::Relaxed);
+                    }
+                }
 
-            process_data.distances[[neighbor.y as usize, neighbor.x as usize]] = distance;
-            let pixel = inpaint_pixel(
-                &process_data.process_image,
-                neighbor.as_usizevec2(),
-                resolution,
-                &mut process_data.distances,
-                &mut process_data.flags,
-                radius,
-            );
-            process_data
-                .process_image
-                .slice_mut(s![neighbor.y, neighbor.x, ..])
-                .assign(&pixel);
+                // Process the jobs that no other thread has started, then wait for the rest
+                drop(job_sender);
+                loop {
+                    let job = job_receiver
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .try_recv();
+                    let Ok(mut job) = job else {
+                        break;
+                    };
+                    outstanding -= 1;
+                    let result = match cancelled.load(Ordering::Relaxed) {
+                        true => Err(Error::Cancelled),
+                        false => run(&mut job, &mut report),
+                    };
+                    if !state.finish((job, result)) {
+                        cancelled.store(true, Ordering::Relaxed);
+                    }
+                }
+                while outstanding > 0 {
+                    let Ok(done) = done_receiver.recv() else {
+                        break;
+                    };
+                    outstanding -= 1;
+                    state.finish(done);
+                }
+            });
+            self.thread_workspaces = state.free;
+            self.region_inpainted = state.inpainted;
 
-            process_data.flags[[neighbor.y as usize, neighbor.x as usize]] = Flag::Band;
-            process_data
-                .heap
-                .push(Reverse(QueueItem::new(distance, neighbor.as_usizevec2())));
+            if let Some(failure) = state.error {
+                return Err(failure);
+            }
+            let current = filled.load(Ordering::Relaxed);
+            if reported != current && progress(current, total).is_break() {
+                return Err(Error::Cancelled);
+            }
         }
+
+        Ok(crops)
     }
-    image
-        .indexed_iter_mut()
-        .for_each(|((y, x, channel), value)| {
-            *value = process_data.process_image[[y, x, channel]].as_();
-        });
 
-    Ok(())
+    /// Write the inpainted pixels of the regions into the output, converting every value
+    fn write_inpainted<OutputType, MaskType>(
+        &self,
+        output: &mut ArrayViewMut3<OutputType>,
+        mask: &ArrayView2<MaskType>,
+        crops: &[(Region, Region)],
+        convert: impl Fn(f32) -> OutputType,
+    ) where
+        MaskType: AsPrimitive<f32> + Copy + 'static,
+    {
+        let channels = output.dim().2.max(1);
+        let regions = crops.iter().map(|(region, _)| *region);
+
+        #[cfg(not(feature = "rayon"))]
+        let inpainted = self.inpainted.chunks_exact(channels);
+
+        #[cfg(feature = "rayon")]
+        let inpainted = self
+            .region_inpainted
+            .iter()
+            .flat_map(|inpainted| inpainted.chunks_exact(channels));
+
+        write_stored(output, mask, regions, inpainted, convert);
+    }
+
+    /// Convert the cropped part of the image and mask into the buffers used for processing
+    ///
+    /// The values of the image are divided by `max_value`.
+    pub(crate) fn load_crop<ImageType, MaskType>(
+        &mut self,
+        image: &ArrayView3<ImageType>,
+        mask: &ArrayView2<MaskType>,
+        crop: Region,
+        max_value: f32,
+    ) where
+        ImageType: AsPrimitive<f32> + Copy,
+        MaskType: AsPrimitive<f32> + Copy + 'static,
+    {
+        self.process_image.clear();
+        self.process_image.extend(
+            image
+                .slice(s![crop.min.y..crop.max.y, crop.min.x..crop.max.x, ..])
+                .iter()
+                .map(|pixel| pixel.as_() / max_value),
+        );
+        self.pixel.clear();
+        self.pixel.resize(image.dim().2, 0.0);
+        self.mask.clear();
+        self.mask.extend(
+            mask.slice(s![crop.min.y..crop.max.y, crop.min.x..crop.max.x])
+                .iter()
+                .map(|value| mask_value_to_flag(*value)),
+        );
+    }
+
+    /// Inpaint the crop that has been loaded into the buffers.
+    ///
+    /// Inpainted pixels within the counted region, which is relative to the crop,
+    /// are reported to the progress callback.
+    pub(crate) fn process(
+        &mut self,
+        crop: Region,
+        options: &TeleaOptions,
+        counted: Region,
+        progress: &mut dyn FnMut(usize) -> ControlFlow<()>,
+    ) -> Result<()> {
+        let mut process_data = ProcessData::new(self, crop.max - crop.min, options)?;
+        process_data.march(options, counted, progress)
+    }
+
+    /// Store the inpainted pixels of the region from the processed crop, to write them later
+    fn store_region(&self, region: Region, crop: Region, inpainted: &mut Vec<f32>) {
+        let crop_resolution = crop.max - crop.min;
+        let channels = self.pixel.len();
+        for y in region.min.y..region.max.y {
+            for x in region.min.x..region.max.x {
+                let local = USizeVec2::new(x, y) - crop.min;
+                let index = local.y * crop_resolution.x + local.x;
+                if self.mask[index] != Flag::Band {
+                    continue;
+                }
+                inpainted.extend_from_slice(
+                    &self.process_image[index * channels..(index + 1) * channels],
+                );
+            }
+        }
+    }
+
+    /// Values of the pixel at the position within the processed crop,
+    /// or `None` if the pixel was not inpainted.
+    pub(crate) fn inpainted_pixel(&self, crop: Region, position: USizeVec2) -> Option<&[f32]> {
+        let local = position - crop.min;
+        let index = local.y * (crop.max.x - crop.min.x) + local.x;
+        if self.mask[index] != Flag::Band {
+            return None;
+        }
+        let channels = self.pixel.len();
+        Some(&self.process_image[index * channels..(index + 1) * channels])
+    }
+
+    /// Mark the pixel at the position within the loaded crop as known, with the given values.
+    pub(crate) fn set_known(&mut self, crop: Region, position: USizeVec2, values: &[f32]) {
+        let local = position - crop.min;
+        let index = local.y * (crop.max.x - crop.min.x) + local.x;
+        let channels = self.pixel.len();
+        self.mask[index] = Flag::Known;
+        self.process_image[index * channels..(index + 1) * channels].copy_from_slice(values);
+    }
+}
+
+/// Write the stored inpainted pixels of all regions into the image
+fn write_stored<'a, ImageType, MaskType>(
+    image: &mut ArrayViewMut3<ImageType>,
+    mask: &ArrayView2<MaskType>,
+    regions: impl Iterator<Item = Region>,
+    mut inpainted: impl Iterator<Item = &'a [f32]>,
+    convert: impl Fn(f32) -> ImageType,
+) where
+    MaskType: AsPrimitive<f32> + Copy + 'static,
+{
+    for region in regions {
+        for y in region.min.y..region.max.y {
+            for x in region.min.x..region.max.x {
+                if !is_masked(mask[[y, x]]) {
+                    continue;
+                }
+                let processed = match inpainted.next() {
+                    Some(processed) => processed,
+                    None => return,
+                };
+                for (value, processed) in image.slice_mut(s![y, x, ..]).iter_mut().zip(processed) {
+                    *value = convert(*processed);
+                }
+            }
+        }
+    }
+}
+
+/// Data structure that stores the processing data.
+///
+/// All arrays are views into the buffers of the [`TeleaWorkspace`].
+struct ProcessData<'a> {
+    resolution: USizeVec2,
+    distances: DistanceArray<'a>,
+    process_image: Image<'a, f32>,
+    flags: FlagArray<'a>,
+    heap: &'a mut NarrowBand,
+    pixel: &'a mut [f32],
+    kernel: &'a Kernel,
+}
+
+impl<'a> ProcessData<'a> {
+    /// Initialize the process data and precompute the distances, flags and fill heap
+    pub fn new(
+        workspace: &'a mut TeleaWorkspace,
+        resolution: USizeVec2,
+        options: &TeleaOptions,
+    ) -> Result<Self> {
+        let TeleaWorkspace {
+            process_image,
+            mask,
+            distances,
+            flags,
+            outside_flags,
+            heap,
+            outside_heap,
+            pixel,
+            kernel,
+            ..
+        } = workspace;
+        let shape = (resolution.y, resolution.x);
+        let pixel_count = resolution.x * resolution.y;
+
+        let process_image =
+            ArrayViewMut3::from_shape((resolution.y, resolution.x, pixel.len()), process_image)?;
+        let mask_array = ArrayView2::from_shape(shape, mask.as_slice())?;
+        distances.clear();
+        distances.resize(pixel_count, MAX);
+        let mut distances = ArrayViewMut2::from_shape(shape, distances)?;
+        flags.clear();
+        flags.extend(
+            mask_array
+                .iter()
+                .map(|f| if *f == Flag::Band { Flag::Inside } else { *f }),
+        );
+        let mut flags = ArrayViewMut2::from_shape(shape, flags)?;
+        heap.reset(options.narrow_band);
+
+        for ((y, x), item) in mask_array.indexed_iter() {
+            if *item == Flag::Known {
+                continue;
+            }
+            let coordinates = USizeVec2::new(x, y);
+            let neighbors = get_neighbors(coordinates.as_ivec2(), options.connectivity);
+            for neighbor in neighbors {
+                if neighbor.y < 0
+                    || neighbor.y >= resolution.y as i32
+                    || neighbor.x < 0
+                    || neighbor.x >= resolution.x as i32
+                {
+                    continue;
+                };
+                if flags[[neighbor.y as usize, neighbor.x as usize]] == Flag::Band {
+                    continue;
+                }
+
+                if mask_array[[neighbor.y as usize, neighbor.x as usize]] == Flag::Known {
+                    flags[[neighbor.y as usize, neighbor.x as usize]] = Flag::Band;
+                    distances[[neighbor.y as usize, neighbor.x as usize]] = 0.0;
+                    heap.push(QueueItem::new(0.0, neighbor.as_usizevec2()));
+                }
+            }
+        }
+        if heap.is_empty() && flags.iter().any(|flag| *flag == Flag::Inside) {
+            return Err(Error::NoKnownPixels);
+        }
+
+        match options.distance_method {
+            DistanceMethod::FastMarching => {
+                outside_flags.clear();
+                outside_flags.resize(pixel_count, Flag::Known);
+                let mut outside_flags = ArrayViewMut2::from_shape(shape, outside_flags)?;
+                compute_outside_distances(
+                    resolution,
+                    &mut distances,
+                    &flags,
+                    heap,
+                    options,
+                    &mut outside_flags,
+                    outside_heap,
+                )?
+            }
+            DistanceMethod::Euclidean => {
+                compute_euclidean_distances(resolution, &mut distances, &flags, options.radius)
+            }
+        }
+
+        kernel.update(options.radius, resolution.x);
+
+        Ok(Self {
+            resolution,
+            distances,
+            process_image,
+            flags,
+            heap,
+            pixel,
+            kernel,
+        })
+    }
+
+    /// March the narrow band into the mask and inpaint every pixel that is reached.
+    ///
+    /// The amount of inpainted pixels within the counted region is reported to the progress
+    /// callback, which cancels the marching when it breaks.
+    pub fn march(
+        &mut self,
+        options: &TeleaOptions,
+        counted: Region,
+        progress: &mut dyn FnMut(usize) -> ControlFlow<()>,
+    ) -> Result<()> {
+        let resolution = self.resolution;
+        let mut filled = 0;
+        while !self.heap.is_empty() {
+            let coordinates = if let Some(node) = self.heap.pop() {
+                node.coordinates
+            } else {
+                return Err(Error::HeapDoesNotContainData);
+            };
+            self.flags[[coordinates.y, coordinates.x]] = Flag::Known;
+
+            let neighbors = get_neighbors(coordinates.as_ivec2(), options.connectivity);
+
+            for neighbor in neighbors {
+                if neighbor.y >= resolution.y as i32 || neighbor.x >= resolution.x as i32 {
+                    continue;
+                }
+
+                let distance = match options.distance_method {
+                    DistanceMethod::FastMarching => get_eikonal(
+                        resolution,
+                        &mut self.distances,
+                        &mut self.flags,
+                        neighbor,
+                        options,
+                    ),
+                    DistanceMethod::Euclidean => {
+                        get_euclidean(resolution, &self.distances, &self.flags, neighbor)
+                    }
+                };
+                let distance = match distance {
+                    Some(value) => value,
+                    None => continue,
+                };
+
+                self.distances[[neighbor.y as usize, neighbor.x as usize]] = distance;
+                let inpainted = inpaint_pixel(
+                    &self.process_image,
+                    neighbor.as_usizevec2(),
+                    resolution,
+                    &self.distances,
+                    &self.flags,
+                    self.kernel,
+                    self.pixel,
+                );
+                if !inpainted {
+                    // No known pixel within the radius, so fall back to the known pixel
+                    // from which the band reached this pixel, which is its nearest one
+                    for (value, known) in self.pixel.iter_mut().zip(self.process_image.slice(s![
+                        coordinates.y,
+                        coordinates.x,
+                        ..
+                    ])) {
+                        *value = *known;
+                    }
+                }
+                for (value, inpainted) in self
+                    .process_image
+                    .slice_mut(s![neighbor.y, neighbor.x, ..])
+                    .iter_mut()
+                    .zip(self.pixel.iter())
+                {
+                    *value = *inpainted;
+                }
+
+                self.flags[[neighbor.y as usize, neighbor.x as usize]] = Flag::Band;
+                self.heap
+                    .push(QueueItem::new(distance, neighbor.as_usizevec2()));
+
+                if counted.contains(neighbor.as_usizevec2()) {
+                    filled += 1;
+                    if filled == PROGRESS_INTERVAL {
+                        report_progress(progress, filled)?;
+                        filled = 0;
+                    }
+                }
+            }
+        }
+        report_progress(progress, filled)
+    }
+}
+
+/// Report the inpainted pixels, returning an error when the progress callback cancels
+fn report_progress(
+    progress: &mut dyn FnMut(usize) -> ControlFlow<()>,
+    filled: usize,
+) -> Result<()> {
+    match progress(filled) {
+        ControlFlow::Continue(()) => Ok(()),
+        ControlFlow::Break(()) => Err(Error::Cancelled),
+    }
+}
+
+/// Margin between regions of the mask, for which the regions still influence each other.
+///
+/// Regions closer than twice the margin are merged, which is at least the context,
+/// so the crop of a region never contains masked pixels of another region.
+pub(crate) fn region_separation(options: &TeleaOptions) -> usize {
+    region_context(options).div_ceil(2)
+}
+
+/// Margin around a region that is needed to inpaint it the same as the full image.
+///
+/// This covers the radius of considered pixels and the band of outside distances.
+pub(crate) fn region_context(options: &TeleaOptions) -> usize {
+    2 * options.radius.max(0) as usize + 3
+}
+
+/// ## Inpaint the input image according to the mask provided.
+///
+/// 3d arrays are expected for inpainting of the image, while 2d array is expected for mask.
+/// As the mask consists of only one mask channel.
+///
+/// In the image array, the rows is the height, the columns is the width
+/// and the dimensions are the channels.
+///
+/// Integer images are rounded to the nearest value and saturated to the range of their type.
+/// See [`OutputConversion`] for how NaN values are handled.
+///
+/// Returns [`Error::NoKnownPixels`] when the mask covers the full image.
+///
+/// ### Arguments:
+///
+/// * `image`: array to inpaint.
+/// * `mask`: mask that defines the region that will be inpainted
+/// * `radius`: radius of near pixels that are considered for npainting.
+///
+/// ### Example
+/// ```rust
+/// use inpaint::telea_inpaint;
+/// use ndarray::{Array2, Array3};
+/// use glam::USizeVec2;
+///
+/// let resolution = USizeVec2::new(1920, 1080);
+/// // obviously you need to use actual data, this is just an example
+/// let mut input_image = Array3::from_elem((resolution.y, resolution.x, 4), 0.0);
+/// let mask = Array2::from_elem((resolution.y, resolution.x), 0.0);
+///
+/// telea_inpaint(&mut input_image.view_mut(), &mask.view(), 1).unwrap();
+/// ```
+pub fn telea_inpaint<ImageType, MaskType>(
+    image: &mut ArrayViewMut3<ImageType>,
+    mask: &ArrayView2<MaskType>,
+    radius: i32,
+) -> Result<()>
+where
+    ImageType: AsPrimitive<f32> + Copy,
+    f32: num_traits::AsPrimitive<ImageType>,
+    MaskType: AsPrimitive<f32> + Copy + 'static,
+{
+    telea_inpaint_with_options(image, mask, &TeleaOptions::new(radius))
+}
+
+/// ## Inpaint the input image according to the mask provided into a new array.
+///
+/// Same as [`telea_inpaint`], but leaves the input image untouched and returns the
+/// inpainted image instead. The image is written into the new array in a single pass
+/// through [`telea_inpaint_into`].
+///
+/// ### Arguments:
+///
+/// * `image`: array to inpaint.
+/// * `mask`: mask that defines the region that will be inpainted
+/// * `radius`: radius of near pixels that are considered for inpainting.
+///
+/// ### Example
+/// ```rust
+/// use inpaint::telea_inpainted;
+/// use ndarray::{Array2, Array3};
+///
+/// let input_image = Array3::from_elem((1080, 1920, 4), 0.0);
+/// let mask = Array2::from_elem((1080, 1920), 0.0);
+///
+/// let output_image = telea_inpainted(&input_image.view(), &mask.view(), 1).unwrap();
+/// ```
+pub fn telea_inpainted<ImageType, MaskType>(
+    image: &ArrayView3<ImageType>,
+    mask: &ArrayView2<MaskType>,
+    radius: i32,
+) -> Result<Array3<ImageType>>
+where
+    ImageType: AsPrimitive<f32> + Copy + Default,
+    f32: num_traits::AsPrimitive<ImageType>,
+    MaskType: AsPrimitive<f32> + Copy + 'static,
+{
+    let mut output = Array3::default(image.dim());
+    telea_inpaint_into(
+        image,
+        mask,
+        &mut output.view_mut(),
+        &TeleaOptions::new(radius),
+        &OutputConversion::between::<ImageType, ImageType>(),
+    )?;
+    Ok(output)
+}
+
+/// ## Inpaint the input image according to the mask provided, with the provided options.
+///
+/// Same as [`telea_inpaint`], but allows to configure the algorithm through [`TeleaOptions`].
+///
+/// ### Arguments:
+///
+/// * `image`: array to inpaint.
+/// * `mask`: mask that defines the region that will be inpainted
+/// * `options`: options to configure the algorithm.
+///
+/// ### Example
+/// ```rust
+/// use inpaint::{DistanceMethod, TeleaOptions, telea_inpaint_with_options};
+/// use ndarray::{Array2, Array3};
+///
+/// let mut input_image = Array3::from_elem((1080, 1920, 4), 0.0);
+/// let mask = Array2::from_elem((1080, 1920), 0.0);
+/// let options = TeleaOptions {
+///     distance_method: DistanceMethod::Euclidean,
+///     ..TeleaOptions::new(5)
+/// };
+///
+/// telea_inpaint_with_options(&mut input_image.view_mut(), &mask.view(), &options).unwrap();
+/// ```
+pub fn telea_inpaint_with_options<ImageType, MaskType>(
+    image: &mut ArrayViewMut3<ImageType>,
+    mask: &ArrayView2<MaskType>,
+    options: &TeleaOptions,
+) -> Result<()>
+where
+    ImageType: AsPrimitive<f32> + Copy,
+    f32: num_traits::AsPrimitive<ImageType>,
+    MaskType: AsPrimitive<f32> + Copy + 'static,
+{
+    TeleaWorkspace::new().telea_inpaint(image, mask, options)
+}
+
+/// ## Inpaint the input image according to the mask provided into an output of another type.
+///
+/// Unlike [`telea_inpaint`], which casts the inpainted values back to the type of the image,
+/// the values are converted with an explicit [`OutputConversion`]. This allows rounding and
+/// clamping integer outputs, or scaling between value ranges.
+///
+/// ### Arguments:
+///
+/// * `image`: array to inpaint, which is left untouched.
+/// * `mask`: mask that defines the region that will be inpainted
+/// * `output`: array with the same shape as the image, that receives the result.
+/// * `options`: options to configure the algorithm.
+/// * `conversion`: conversion of the values from the image to the output.
+///
+/// ### Example
+/// ```rust
+/// use inpaint::{OutputConversion, TeleaOptions, telea_inpaint_into};
+/// use ndarray::{Array2, Array3};
+///
+/// let input_image = Array3::<u8>::from_elem((1080, 1920, 3), 128);
+/// let mask = Array2::<u8>::from_elem((1080, 1920), 0);
+/// let mut output_image = Array3::<f32>::zeros((1080, 1920, 3));
+///
+/// telea_inpaint_into(
+///     &input_image.view(),
+///     &mask.view(),
+///     &mut output_image.view_mut(),
+///     &TeleaOptions::new(5),
+///     &OutputConversion::between::<u8, f32>(),
+/// )
+/// .unwrap();
+/// ```
+pub fn telea_inpaint_into<InputType, OutputType, MaskType>(
+    image: &ArrayView3<InputType>,
+    mask: &ArrayView2<MaskType>,
+    output: &mut ArrayViewMut3<OutputType>,
+    options: &TeleaOptions,
+    conversion: &OutputConversion,
+) -> Result<()>
+where
+    InputType: AsPrimitive<f32> + Copy,
+    OutputType: Copy + 'static,
+    f32: num_traits::AsPrimitive<OutputType>,
+    MaskType: AsPrimitive<f32> + Copy + 'static,
+{
+    TeleaWorkspace::new().telea_inpaint_into(image, mask, output, options, conversion)
+}
+
+/// ## Inpaint the input image according to the mask provided, while reporting the progress.
+///
+/// Same as [`telea_inpaint_with_options`], but calls `progress` with the amount of inpainted
+/// pixels and the total amount of masked pixels while inpainting.
+/// When the callback returns [`ControlFlow::Break`], inpainting stops with
+/// [`Error::Cancelled`] and the image is left untouched.
+///
+/// ### Arguments:
+///
+/// * `image`: array to inpaint.
+/// * `mask`: mask that defines the region that will be inpainted
+/// * `options`: options to configure the algorithm.
+/// * `progress`: callback that receives the inpainted and total amount of pixels.
+///
+/// ### Example
+/// ```rust
+/// use core::ops::ControlFlow;
+/// use inpaint::{TeleaOptions, telea_inpaint_with_progress};
+/// use ndarray::{Array2, Array3};
+///
+/// let mut input_image = Array3::from_elem((1080, 1920, 4), 0.0);
+/// let mask = Array2::from_elem((1080, 1920), 0.0);
+///
+/// telea_inpaint_with_progress(
+///     &mut input_image.view_mut(),
+///     &mask.view(),
+///     &TeleaOptions::new(5),
+///     |filled, total| {
+///         println!("Inpainted {filled} of {total} pixels");
+///         ControlFlow::Continue(())
+///     },
+/// )
+/// .unwrap();
+/// ```
+pub fn telea_inpaint_with_progress<ImageType, MaskType, Progress>(
+    image: &mut ArrayViewMut3<ImageType>,
+    mask: &ArrayView2<MaskType>,
+    options: &TeleaOptions,
+    progress: Progress,
+) -> Result<()>
+where
+    ImageType: AsPrimitive<f32> + Copy,
+    f32: num_traits::AsPrimitive<ImageType>,
+    MaskType: AsPrimitive<f32> + Copy + 'static,
+    Progress: FnMut(usize, usize) -> ControlFlow<()>,
+{
+    TeleaWorkspace::new().telea_inpaint_with_progress(image, mask, options, progress)
 }
 
 #[cfg(test)]
@@ -613,10 +1791,30 @@ mod tests {
     use super::*;
     use image::{DynamicImage, Pixel, Rgba32FImage};
     use image_ndarray::prelude::*;
-    use ndarray::s;
+    use ndarray::{Array2, Array3, ShapeBuilder, s};
     use rstest::rstest;
     use time::OffsetDateTime;
 
+    /// Inpaint the full image at once, without cropping it to the regions of the mask
+    fn inpaint_full_image<MaskType>(
+        image: &Array3<f32>,
+        mask: &Array2<MaskType>,
+        options: &TeleaOptions,
+    ) -> Array3<f32>
+    where
+        MaskType: AsPrimitive<f32> + Copy + 'static,
+    {
+        let full_image = Region::new(USizeVec2::ZERO, USizeVec2::new(mask.ncols(), mask.nrows()));
+        let mut workspace = TeleaWorkspace::new();
+        workspace.load_crop(&image.view(), &mask.view(), full_image, 1.0);
+        workspace
+            .process(full_image, options, full_image, &mut |_| {
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+        Array3::from_shape_vec(image.raw_dim(), workspace.process_image).unwrap()
+    }
+
     #[rstest]
     #[case(
         include_bytes!("../test/images/input/bird.png"),
@@ -755,7 +1953,7 @@ mod tests {
     fn test_inpaint_u8(#[case] image: &[u8], #[case] mask: &[u8], #[case] expected: &[u8]) {
         let mut image = image::load_from_memory_with_format(image, image::ImageFormat::Png)
             .unwrap()
-            .to_luma8();
+            .to_rgb8();
         let mask = image::load_from_memory_with_format(mask, image::ImageFormat::Png)
             .unwrap()
             .to_luma8();
@@ -788,7 +1986,719 @@ mod tests {
 
         #[cfg(feature = "std")]
         println!("Test got score: {}", comparison_score);
-        assert!(comparison_score >= 0.99); // Slightly lower because of precision
+        assert_eq!(comparison_score, 1.0);
+    }
+
+    #[rstest]
+    #[case(
+        include_bytes!("../test/images/input/bird.png"),
+        include_bytes!("../test/images/mask/medium.png"),
+        include_bytes!("../test/images/expected/telea/bird_medium.png")
+    )]
+    #[case(
+        include_bytes!("../test/images/input/toad.png"),
+        include_bytes!("../test/images/mask/text.png"),
+        include_bytes!("../test/images/expected/telea/toad_text.png")
+    )]
+    #[case(
+        include_bytes!("../test/images/input/pizza.png"),
+        include_bytes!("../test/images/mask/rectangle.png"),
+        include_bytes!("../test/images/expected/telea/pizza_rectangle.png")
+    )]
+    /// Test inpaint with the Euclidean distance transform, which should be close to the eikonal result
+    fn test_inpaint_euclidean(#[case] image: &[u8], #[case] mask: &[u8], #[case] expected: &[u8]) {
+        let mut image = image::load_from_memory_with_format(image, image::ImageFormat::Png)
+            .unwrap()
+            .to_rgba32f();
+        let mask = image::load_from_memory_with_format(mask, image::ImageFormat::Png)
+            .unwrap()
+            .to_luma8();
+        let options = TeleaOptions {
+            distance_method: DistanceMethod::Euclidean,
+            ..TeleaOptions::new(5)
+        };
+
+        telea_inpaint_with_options(
+            &mut image.as_ndarray_mut(),
+            &mask.to_ndarray().slice(ndarray::s![.., .., 0]),
+            &options,
+        )
+        .unwrap();
+
+        let result = DynamicImage::from(image.clone());
+        let expected_image = image::load_from_memory_with_format(expected, image::ImageFormat::Png)
+            .unwrap()
+            .to_rgb8();
+        let comparison_score =
+            image_compare::rgb_hybrid_compare(&result.to_rgb8(), &expected_image)
+                .unwrap()
+                .score;
+
+        #[cfg(feature = "std")]
+        println!("Test got score: {}", comparison_score);
+        assert!(comparison_score >= 0.95);
+    }
+
+    #[test]
+    /// The Euclidean distances inside the mask should be exact, also on the diagonals
+    fn test_euclidean_distances_isotropic() {
+        let resolution = USizeVec2::new(21, 21);
+        let image = Array3::<f32>::zeros((resolution.y, resolution.x, 1));
+        let mask = Array2::from_shape_fn((resolution.y, resolution.x), |(y, x)| {
+            if (5..16).contains(&y) && (5..16).contains(&x) {
+                1.0
+            } else {
+                0.0
+            }
+        });
+        let options = TeleaOptions {
+            distance_method: DistanceMethod::Euclidean,
+            ..TeleaOptions::new(1)
+        };
+
+        let mut workspace = TeleaWorkspace::new();
+        workspace.load_crop(
+            &image.view(),
+            &mask.view(),
+            Region::new(USizeVec2::ZERO, resolution),
+            1.0,
+        );
+        let process_data = ProcessData::new(&mut workspace, resolution, &options).unwrap();
+
+        // Corner of the mask is adjacent to the band both horizontally and vertically
+        assert_eq!(process_data.distances[[5, 5]], 1.0);
+        assert_eq!(process_data.distances[[7, 7]], 3.0);
+        // Diagonal of the band corner is not part of the band, so nearest is axis aligned
+        assert_eq!(process_data.distances[[10, 10]], 6.0);
+        assert_eq!(process_data.distances[[6, 9]], 2.0);
+        // Outside of the mask distances are negative
+        assert_eq!(process_data.distances[[4, 10]], 0.0);
+        assert_eq!(process_data.distances[[2, 10]], -2.0);
+        assert_eq!(process_data.distances[[0, 10]], -MAX);
+    }
+
+    #[rstest]
+    #[case(Connectivity::Eight, EikonalOrder::First)]
+    #[case(Connectivity::Four, EikonalOrder::Second)]
+    #[case(Connectivity::Eight, EikonalOrder::Second)]
+    /// Test inpaint with the alternative marching schemes, which should be close to the default
+    fn test_inpaint_marching_schemes(
+        #[case] connectivity: Connectivity,
+        #[case] eikonal_order: EikonalOrder,
+    ) {
+        let mut image = image::load_from_memory_with_format(
+            include_bytes!("../test/images/input/toad.png"),
+            image::ImageFormat::Png,
+        )
+        .unwrap()
+        .to_rgba32f();
+        let mask = image::load_from_memory_with_format(
+            include_bytes!("../test/images/mask/text.png"),
+            image::ImageFormat::Png,
+        )
+        .unwrap()
+        .to_luma8();
+        let options = TeleaOptions {
+            connectivity,
+            eikonal_order,
+            ..TeleaOptions::new(5)
+        };
+
+        telea_inpaint_with_options(
+            &mut image.as_ndarray_mut(),
+            &mask.to_ndarray().slice(ndarray::s![.., .., 0]),
+            &options,
+        )
+        .unwrap();
+
+        let result = DynamicImage::from(image.clone());
+        let expected_image = image::load_from_memory_with_format(
+            include_bytes!("../test/images/expected/telea/toad_text.png"),
+            image::ImageFormat::Png,
+        )
+        .unwrap()
+        .to_rgb8();
+        let comparison_score =
+            image_compare::rgb_hybrid_compare(&result.to_rgb8(), &expected_image)
+                .unwrap()
+                .score;
+
+        #[cfg(feature = "std")]
+        println!("Test got score: {}", comparison_score);
+        assert!(comparison_score >= 0.95);
+    }
+
+    #[test]
+    /// Marching with eight neighbors and second order should be closer to the exact distances
+    fn test_marching_schemes_accuracy() {
+        let resolution = USizeVec2::new(41, 41);
+        let image = Array3::<f32>::zeros((resolution.y, resolution.x, 1));
+        let mask = Array2::from_shape_fn((resolution.y, resolution.x), |(y, x)| {
+            if y == 20 && x == 20 { 1.0 } else { 0.0 }
+        });
+
+        let max_error = |connectivity, eikonal_order| {
+            let options = TeleaOptions {
+                connectivity,
+                eikonal_order,
+                ..TeleaOptions::new(8)
+            };
+            let mut workspace = TeleaWorkspace::new();
+            workspace.load_crop(
+                &image.view(),
+                &mask.view(),
+                Region::new(USizeVec2::ZERO, resolution),
+                1.0,
+            );
+            let process_data = ProcessData::new(&mut workspace, resolution, &options).unwrap();
+            let mut max_error: f32 = 0.0;
+            for ((y, x), distance) in process_data.distances.indexed_iter() {
+                let exact = ((y as f32 - 20.0).powi(2) + (x as f32 - 20.0).powi(2)).sqrt() - 1.0;
+                if *distance == -MAX || exact > 12.0 || (y == 20 && x == 20) {
+                    continue;
+                }
+                max_error = max_error.max((-distance - exact).abs());
+            }
+            max_error
+        };
+
+        let default_error = max_error(Connectivity::Four, EikonalOrder::First);
+        let improved_error = max_error(Connectivity::Eight, EikonalOrder::Second);
+        #[cfg(feature = "std")]
+        println!("Error default: {default_error}, improved: {improved_error}");
+        assert!(improved_error < default_error);
+    }
+
+    #[rstest]
+    #[case(
+        include_bytes!("../test/images/input/toad.png"),
+        include_bytes!("../test/images/mask/text.png")
+    )]
+    #[case(
+        include_bytes!("../test/images/input/bird.png"),
+        include_bytes!("../test/images/mask/medium.png")
+    )]
+    #[case(
+        include_bytes!("../test/images/input/pizza.png"),
+        include_bytes!("../test/images/mask/rectangle-strokes.png")
+    )]
+    /// Inpainting the cropped regions should give the same result as the full image
+    fn test_inpaint_regions(#[case] image: &[u8], #[case] mask: &[u8]) {
+        let mut image = image::load_from_memory_with_format(image, image::ImageFormat::Png)
+            .unwrap()
+            .to_rgba32f();
+        let mask = image::load_from_memory_with_format(mask, image::ImageFormat::Png)
+            .unwrap()
+            .to_luma8();
+        let mask = mask.to_ndarray().slice(ndarray::s![.., .., 0]).to_owned();
+        let options = TeleaOptions::new(5);
+
+        let expected = inpaint_full_image(&image.as_ndarray().to_owned(), &mask, &options);
+
+        telea_inpaint_with_options(&mut image.as_ndarray_mut(), &mask.view(), &options).unwrap();
+
+        assert_eq!(image.as_ndarray(), expected);
+    }
+
+    #[test]
+    /// Masks touching the image borders should give the same result when cropped
+    fn test_inpaint_regions_on_border() {
+        let resolution = USizeVec2::new(200, 150);
+        let image = Array3::from_shape_fn((resolution.y, resolution.x, 3), |(y, x, channel)| {
+            (y * 3 + x * 7 + channel * 11) as f32 / 1000.0
+        });
+        let mask = Array2::from_shape_fn((resolution.y, resolution.x), |(y, x)| {
+            let corner = y < 10 && x > 185;
+            let blob = (70..80).contains(&y) && (90..97).contains(&x);
+            if corner || blob { 1.0 } else { 0.0 }
+        });
+        let options = TeleaOptions::new(5);
+
+        let expected = inpaint_full_image(&image, &mask, &options);
+
+        let mut result = image.clone();
+        telea_inpaint_with_options(&mut result.view_mut(), &mask.view(), &options).unwrap();
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    /// Holes that are just too far apart to be merged should not influence each other's crop
+    fn test_inpaint_regions_close_holes() {
+        for radius in [1, 2, 5] {
+            let options = TeleaOptions::new(radius);
+            let gap = 2 * radius as usize + 2;
+            for gap in gap..gap + 3 {
+                let image = Array3::from_shape_fn((60, 80, 2), |(y, x, channel)| {
+                    ((y * 5 + x * 3 + channel * 7) % 23) as f32 / 23.0
+                });
+                let mut mask = Array2::<f32>::zeros((60, 80));
+                mask.slice_mut(s![20..30, 10..20]).fill(1.0);
+                mask.slice_mut(s![25..40, 20 + gap..30 + gap]).fill(1.0);
+
+                let resolution = USizeVec2::new(80, 60);
+                let regions = find_regions(
+                    resolution,
+                    |y, x| is_masked(mask[[y, x]]),
+                    region_separation(&options),
+                    &mut Vec::new(),
+                );
+                for region in &regions {
+                    let crop = region.expand(region_context(&options), resolution);
+                    let foreign = regions
+                        .iter()
+                        .filter(|other| *other != region && other.intersects(&crop));
+                    assert_eq!(foreign.count(), 0, "radius {radius}, gap {gap}");
+                }
+
+                let expected = inpaint_full_image(&image, &mask, &options);
+
+                let mut result = image.clone();
+                telea_inpaint_with_options(&mut result.view_mut(), &mask.view(), &options).unwrap();
+
+                assert_eq!(result, expected, "radius {radius}, gap {gap}");
+            }
+        }
+    }
+
+    #[rstest]
+    #[case(
+        include_bytes!("../test/images/input/bird.png"),
+        include_bytes!("../test/images/mask/text.png"),
+        DistanceMethod::FastMarching
+    )]
+    #[case(
+        include_bytes!("../test/images/input/toad.png"),
+        include_bytes!("../test/images/mask/large.png"),
+        DistanceMethod::FastMarching
+    )]
+    #[case(
+        include_bytes!("../test/images/input/pizza.png"),
+        include_bytes!("../test/images/mask/rectangle.png"),
+        DistanceMethod::FastMarching
+    )]
+    #[case(
+        include_bytes!("../test/images/input/toad.png"),
+        include_bytes!("../test/images/mask/medium.png"),
+        DistanceMethod::Euclidean
+    )]
+    /// The bucket queue should give exactly the same result as the binary heap
+    fn test_narrow_band_queues(
+        #[case] image: &[u8],
+        #[case] mask: &[u8],
+        #[case] distance_method: DistanceMethod,
+    ) {
+        let image = image::load_from_memory_with_format(image, image::ImageFormat::Png)
+            .unwrap()
+            .to_rgba32f();
+        let mask = image::load_from_memory_with_format(mask, image::ImageFormat::Png)
+            .unwrap()
+            .to_luma8();
+        let mask = mask.to_ndarray().slice(ndarray::s![.., .., 0]).to_owned();
+
+        let inpaint_with_queue = |narrow_band| {
+            let options = TeleaOptions {
+                distance_method,
+                narrow_band,
+                ..TeleaOptions::new(5)
+            };
+            let mut result = image.as_ndarray().to_owned();
+            telea_inpaint_with_options(&mut result.view_mut(), &mask.view(), &options).unwrap();
+            result
+        };
+
+        assert_eq!(
+            inpaint_with_queue(NarrowBandQueue::Buckets),
+            inpaint_with_queue(NarrowBandQueue::BinaryHeap)
+        );
+    }
+
+    #[test]
+    /// Reusing the workspace for different images and masks should give the same results
+    fn test_workspace_reuse() {
+        let mut workspace = TeleaWorkspace::new();
+        let options = TeleaOptions::new(3);
+
+        for (size, offset) in [(60, 10), (60, 25), (40, 5), (80, 30)] {
+            let image = Array3::from_shape_fn((size, size + 5, 2), |(y, x, channel)| {
+                ((y * 5 + x * 3) % 17 + channel) as f32
+            });
+            let mask = Array2::from_shape_fn((size, size + 5), |(y, x)| {
+                let inside =
+                    (offset..offset + 8).contains(&y) && (offset..offset + 12).contains(&x);
+                if inside { 255u8 } else { 0u8 }
+            });
+
+            let mut expected = image.clone();
+            telea_inpaint_with_options(&mut expected.view_mut(), &mask.view(), &options).unwrap();
+
+            let mut result = image.clone();
+            workspace
+                .telea_inpaint(&mut result.view_mut(), &mask.view(), &options)
+                .unwrap();
+
+            assert_eq!(result, expected);
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    /// Inpainting from within a rayon pool, with more images than threads, shouldn't deadlock
+    fn test_inpaint_within_rayon_pool() {
+        use rayon::prelude::*;
+
+        let image = Array3::from_shape_fn((40, 60, 3), |(y, x, channel)| {
+            ((y * 3 + x * 7) % 23 + channel) as f32
+        });
+        let mask = Array2::from_shape_fn((40, 60), |(y, x)| {
+            let hole = (10..20).contains(&y) && ((5..15).contains(&x) || (40..50).contains(&x));
+            if hole { 255u8 } else { 0u8 }
+        });
+        let mut expected = image.clone();
+        telea_inpaint(&mut expected.view_mut(), &mask.view(), 5).unwrap();
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        let mut images = vec![image; 16];
+        pool.install(|| {
+            images.par_iter_mut().for_each(|image| {
+                telea_inpaint(&mut image.view_mut(), &mask.view(), 5).unwrap();
+            })
+        });
+
+        assert!(images.iter().all(|result| *result == expected));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    /// Many small holes should only keep a workspace per thread and the inpainted pixels
+    fn test_rayon_workspaces_per_thread() {
+        let image = Array3::from_shape_fn((100, 100, 3), |(y, x, channel)| {
+            ((y * 3 + x * 7) % 23 + channel) as f32
+        });
+        let mask = Array2::from_shape_fn((100, 100), |(y, x)| {
+            if y % 20 == 10 && x % 20 == 10 {
+                255u8
+            } else {
+                0u8
+            }
+        });
+        let options = TeleaOptions::new(3);
+        let expected = inpaint_full_image(&image, &mask, &options);
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(3)
+            .build()
+            .unwrap();
+        let mut workspace = TeleaWorkspace::new();
+        let mut result = image.clone();
+        pool.install(|| {
+            workspace
+                .telea_inpaint(&mut result.view_mut(), &mask.view(), &options)
+                .unwrap()
+        });
+
+        assert_eq!(result, expected);
+        assert!(workspace.thread_workspaces.len() <= 2);
+        assert_eq!(workspace.region_inpainted.len(), 25);
+        assert!(
+            workspace
+                .region_inpainted
+                .iter()
+                .all(|pixels| pixels.len() == 3)
+        );
+    }
+
+    /// Load the bird image with the large mask as arrays
+    fn load_bird_large() -> (Array3<u8>, Array2<u8>) {
+        let image = image::load_from_memory_with_format(
+            include_bytes!("../test/images/input/bird.png"),
+            image::ImageFormat::Png,
+        )
+        .unwrap()
+        .to_rgb8();
+        let mask = image::load_from_memory_with_format(
+            include_bytes!("../test/images/mask/large.png"),
+            image::ImageFormat::Png,
+        )
+        .unwrap()
+        .to_luma8();
+        let shape = (image.height() as usize, image.width() as usize);
+        (
+            Array3::from_shape_vec((shape.0, shape.1, 3), image.into_raw()).unwrap(),
+            Array2::from_shape_vec(shape, mask.into_raw()).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_inpainted() {
+        let (image, mask) = load_bird_large();
+        let original = image.clone();
+
+        let output = telea_inpainted(&image.view(), &mask.view(), 5).unwrap();
+
+        let mut expected = image.clone();
+        telea_inpaint(&mut expected.view_mut(), &mask.view(), 5).unwrap();
+        assert_eq!(output, expected);
+        assert_eq!(image, original);
+    }
+
+    #[test]
+    fn test_inpaint_into_float() {
+        let (image, mask) = load_bird_large();
+        let original = image.clone();
+        let options = TeleaOptions::default();
+
+        let mut output = Array3::<f32>::zeros(image.raw_dim());
+        telea_inpaint_into(
+            &image.view(),
+            &mask.view(),
+            &mut output.view_mut(),
+            &options,
+            &OutputConversion::between::<u8, f32>(),
+        )
+        .unwrap();
+
+        let mut expected = image.mapv(|value| value as f32 / 255.0);
+        telea_inpaint_with_options(&mut expected.view_mut(), &mask.view(), &options).unwrap();
+        assert_eq!(image, original);
+        for (output, expected) in output.iter().zip(expected.iter()) {
+            assert!((output - expected).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_inpaint_into_rounded() {
+        let (image, mask) = load_bird_large();
+        let image = image.mapv(|value| value as f32);
+        let options = TeleaOptions::default();
+
+        let mut output = Array3::<u8>::zeros(image.raw_dim());
+        telea_inpaint_into(
+            &image.view(),
+            &mask.view(),
+            &mut output.view_mut(),
+            &options,
+            &OutputConversion::between::<u8, u8>(),
+        )
+        .unwrap();
+
+        let mut expected = image.clone();
+        telea_inpaint_with_options(&mut expected.view_mut(), &mask.view(), &options).unwrap();
+        assert_eq!(output, expected.mapv(|value| value.round() as u8));
+
+        let mut output = Array3::<u8>::zeros((3, 3, 3));
+        let result = telea_inpaint_into(
+            &image.view(),
+            &mask.view(),
+            &mut output.view_mut(),
+            &options,
+            &OutputConversion::default(),
+        );
+        assert!(matches!(result, Err(Error::DimensionMismatch)));
+    }
+
+    #[cfg(feature = "half")]
+    #[test]
+    fn test_inpaint_half() {
+        let (image, mask) = load_bird_large();
+        let mut expected = image.mapv(|value| value as f32 / 255.0);
+        telea_inpaint(&mut expected.view_mut(), &mask.view(), 5).unwrap();
+
+        let mut f16_image = image.mapv(|value| half::f16::from_f32(value as f32 / 255.0));
+        let mut bf16_image = image.mapv(|value| half::bf16::from_f32(value as f32 / 255.0));
+        telea_inpaint(&mut f16_image.view_mut(), &mask.view(), 5).unwrap();
+        telea_inpaint(&mut bf16_image.view_mut(), &mask.view(), 5).unwrap();
+
+        for ((expected, f16), bf16) in expected.iter().zip(&f16_image).zip(&bf16_image) {
+            assert!((expected - f16.to_f32()).abs() < 1e-3);
+            assert!((expected - bf16.to_f32()).abs() < 1e-2);
+        }
+    }
+
+    #[test]
+    fn test_progress() {
+        let (image, mask) = load_bird_large();
+        let masked = mask.iter().filter(|value| **value > 0).count();
+
+        let mut reports = Vec::new();
+        let mut result = image.clone();
+        telea_inpaint_with_progress(
+            &mut result.view_mut(),
+            &mask.view(),
+            &TeleaOptions::default(),
+            |filled, total| {
+                reports.push((filled, total));
+                ControlFlow::Continue(())
+            },
+        )
+        .unwrap();
+
+        let mut expected = image.clone();
+        telea_inpaint(&mut expected.view_mut(), &mask.view(), 5).unwrap();
+        assert_eq!(result, expected);
+        assert!(reports.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+        assert!(reports.iter().all(|(_, total)| *total == masked));
+        assert_eq!(reports.last(), Some(&(masked, masked)));
+    }
+
+    #[rstest]
+    #[case(0.0)]
+    #[case(0.5)]
+    #[case(1.0)]
+    /// Cancelling should leave the image untouched, no matter when it is cancelled
+    fn test_cancel(#[case] cancel_at: f32) {
+        let (image, mask) = load_bird_large();
+
+        let mut result = image.clone();
+        let outcome = telea_inpaint_with_progress(
+            &mut result.view_mut(),
+            &mask.view(),
+            &TeleaOptions::default(),
+            |filled, total| match filled as f32 >= cancel_at * total as f32 {
+                true => ControlFlow::Break(()),
+                false => ControlFlow::Continue(()),
+            },
+        );
+
+        assert!(matches!(outcome, Err(Error::Cancelled)));
+        assert_eq!(result, image);
+    }
+
+    #[test]
+    fn test_kernel_disk() {
+        let mut kernel = Kernel::default();
+        kernel.update(5, 40);
+
+        // Lattice points within a circle of radius 5, without the center
+        assert_eq!(kernel.items.len(), 80);
+        for item in &kernel.items {
+            assert!(item.offset.length_squared() <= 25);
+            assert_eq!(
+                item.index_offset,
+                item.offset.y as isize * 40 + item.offset.x as isize
+            );
+        }
+    }
+
+    #[test]
+    /// The flattened interior path should give the same result as the checked border path
+    fn test_inpaint_pixel_interior() {
+        let resolution = USizeVec2::new(24, 20);
+        let shape = (resolution.y, resolution.x);
+        let mut image = Array3::from_shape_fn((resolution.y, resolution.x, 3), |(y, x, c)| {
+            ((y * 7 + x * 13 + c * 5) % 23) as f32
+        });
+        let mut distances =
+            Array2::from_shape_fn(shape, |(y, x)| ((y as f32 - 10.0).abs() + x as f32) * 0.3);
+        let mut flags = Array2::from_shape_fn(shape, |(y, x)| {
+            if (8..12).contains(&y) && (9..15).contains(&x) {
+                Flag::Inside
+            } else {
+                Flag::Known
+            }
+        });
+        // Column major copies are not contiguous in row major order, which forces the border path
+        let mut image_columns = Array3::zeros(image.raw_dim().f());
+        image_columns.assign(&image);
+        let mut distances_columns = Array2::zeros(distances.raw_dim().f());
+        distances_columns.assign(&distances);
+        let mut flags_columns = Array2::from_elem(flags.raw_dim().f(), Flag::Known);
+        flags_columns.assign(&flags);
+
+        let mut kernel = Kernel::default();
+        kernel.update(6, resolution.x);
+        let mut expected = [0.0; 3];
+        let mut result = [0.0; 3];
+        for coordinate in [USizeVec2::new(9, 8), USizeVec2::new(12, 10)] {
+            inpaint_pixel(
+                &image_columns.view_mut(),
+                coordinate,
+                resolution,
+                &distances_columns.view_mut(),
+                &flags_columns.view_mut(),
+                &kernel,
+                &mut expected,
+            );
+            inpaint_pixel(
+                &image.view_mut(),
+                coordinate,
+                resolution,
+                &distances.view_mut(),
+                &flags.view_mut(),
+                &kernel,
+                &mut result,
+            );
+            assert_eq!(result, expected);
+        }
+    }
+
+    #[test]
+    fn test_fully_masked() {
+        let mut image = Array3::<f32>::from_elem((20, 30, 3), 0.5);
+        let mask = Array2::<u8>::from_elem((20, 30), 255);
+
+        let result = telea_inpaint(&mut image.view_mut(), &mask.view(), 5);
+        assert!(matches!(result, Err(Error::NoKnownPixels)));
+        assert!(image.iter().all(|value| *value == 0.5));
+    }
+
+    #[test]
+    fn test_empty_mask() {
+        let (image, _) = load_bird_large();
+        let mask = Array2::<u8>::zeros((image.dim().0, image.dim().1));
+
+        let mut result = image.clone();
+        telea_inpaint(&mut result.view_mut(), &mask.view(), 5).unwrap();
+        assert_eq!(result, image);
+    }
+
+    #[test]
+    fn test_no_known_pixels_in_radius() {
+        // Without a radius no pixel is within the kernel, so every pixel falls back
+        // to the known pixel next to it
+        let mut image = Array3::<f32>::zeros((10, 10, 1));
+        image.slice_mut(s![.., 0..5, ..]).fill(0.25);
+        image.slice_mut(s![.., 5..10, ..]).fill(0.75);
+        let mut mask = Array2::<u8>::zeros((10, 10));
+        mask.slice_mut(s![3..7, 3..7]).fill(255);
+
+        let mut result = image.clone();
+        telea_inpaint(&mut result.view_mut(), &mask.view(), 0).unwrap();
+        assert!(result.iter().all(|value| *value == 0.25 || *value == 0.75));
+    }
+
+    #[rstest]
+    #[case(1)]
+    #[case(3)]
+    #[case(4)]
+    /// The specialized accumulations should give exactly the same result as the generic one
+    fn test_accumulate_pixels(#[case] channels: usize) {
+        let image: Vec<f32> = (0..40 * channels)
+            .map(|value| ((value * 37) % 101) as f32 / 7.0)
+            .collect();
+        let neighbors: Vec<(usize, f32)> = (0..40)
+            .step_by(3)
+            .map(|index| (index, 1.0 / (index as f32 + 0.5)))
+            .collect();
+
+        let mut expected = vec![0.0; channels];
+        let expected_weight = accumulate_pixels(&image, neighbors.iter().copied(), &mut expected);
+
+        let mut result = vec![0.0; channels];
+        let weight = match channels {
+            1 => accumulate_single_channel(&image, neighbors.iter().copied(), &mut result),
+            #[cfg(feature = "simd")]
+            3 => accumulate_pixels_simd::<3>(&image, neighbors.iter().copied(), &mut result),
+            #[cfg(feature = "simd")]
+            4 => accumulate_pixels_simd::<4>(&image, neighbors.iter().copied(), &mut result),
+            _ => accumulate_pixels(&image, neighbors.iter().copied(), &mut result),
+        };
+
+        assert_eq!(weight, expected_weight);
+        assert_eq!(result, expected);
     }
 
     #[test]
@@ -0,0 +1,611 @@
+/// Tiled inpainting of images that don't fit in memory.
+///
+/// The image and mask are requested in tiles from callbacks, so they can be streamed from
+/// and to disk. The mask is first scanned tile by tile to find the independent regions.
+/// Afterwards every output tile is assembled by inpainting the regions that overlap it,
+/// each within a crop that has enough context to match inpainting the full image.
+/// Regions that are too large for the tile size are inpainted in slices of one tile.
+use crate::conversion::{OutputConversion, value_range};
+use crate::error::{Error, Result};
+use crate::region::{Region, find_regions, merge_regions};
+use crate::telea::{TeleaOptions, TeleaWorkspace, is_masked, region_context, region_separation};
+use core::ops::ControlFlow;
+use glam::USizeVec2;
+use ndarray::{Array2, Array3, ArrayView3, s};
+use num_traits::AsPrimitive;
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+#[cfg(not(feature = "std"))]
+use alloc::{collections::BTreeMap, vec::Vec};
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Rectangular part of the image that is requested from or written to the callbacks.
+pub struct TileRegion {
+    /// Column of the left most pixel.
+    pub x: usize,
+    /// Row of the top most pixel.
+    pub y: usize,
+    /// Amount of columns.
+    pub width: usize,
+    /// Amount of rows.
+    pub height: usize,
+}
+
+impl From<Region> for TileRegion {
+    fn from(region: Region) -> Self {
+        Self {
+            x: region.min.x,
+            y: region.min.y,
+            width: region.max.x - region.min.x,
+            height: region.max.y - region.min.y,
+        }
+    }
+}
+
+/// Grid of tiles that covers the full resolution
+fn tiles(resolution: USizeVec2, tile_size: usize) -> impl Iterator<Item = Region> {
+    let tile_size = tile_size.max(1);
+    (0..resolution.y).step_by(tile_size).flat_map(move |y| {
+        (0..resolution.x).step_by(tile_size).map(move |x| {
+            let min = USizeVec2::new(x, y);
+            Region::new(min, (min + tile_size).min(resolution))
+        })
+    })
+}
+
+/// Check that the array returned by a callback has the shape of the requested region
+fn check_shape(shape: &[usize], region: Region) -> Result<()> {
+    let size = region.max - region.min;
+    if shape[0] != size.y || shape[1] != size.x {
+        return Err(Error::DimensionMismatch);
+    }
+    Ok(())
+}
+
+/// Inpainted pixels of a region, kept until every tile that overlaps the region is written
+struct StoredRegion {
+    region: Region,
+    values: Array3<f32>,
+    inpainted: Array2<bool>,
+}
+
+impl StoredRegion {
+    /// Write the inpainted pixels within the part of the region into the image,
+    /// which starts at the provided origin.
+    fn write<ImageType>(
+        &self,
+        image: &mut Array3<ImageType>,
+        image_origin: USizeVec2,
+        part: Region,
+        convert: impl Fn(f32) -> ImageType,
+    ) {
+        for y in part.min.y..part.max.y {
+            for x in part.min.x..part.max.x {
+                let local = USizeVec2::new(x, y) - self.region.min;
+                if !self.inpainted[[local.y, local.x]] {
+                    continue;
+                }
+                let target = USizeVec2::new(x, y) - image_origin;
+                for (value, inpainted) in image
+                    .slice_mut(s![target.y, target.x, ..])
+                    .iter_mut()
+                    .zip(self.values.slice(s![local.y, local.x, ..]))
+                {
+                    *value = convert(*inpainted);
+                }
+            }
+        }
+    }
+}
+
+impl TeleaWorkspace {
+    /// ## Inpaint an image in tiles, which are read from and written to callbacks.
+    ///
+    /// Only a tile and crops of the regions of the mask that overlap it are kept in memory,
+    /// so the peak memory is bounded by the tile size instead of the full image.
+    ///
+    /// Regions of which the crop fits within three times the tile size are inpainted once,
+    /// the same as in the full image, and kept until every tile that overlaps them is written.
+    /// Larger regions, like big holes or long scratches, are inpainted in slices of one tile,
+    /// each within a crop that extends one tile around it. The pixels of the slices above and
+    /// left of it are known in this crop, so there are no seams between the slices,
+    /// but the inpainted values can differ slightly from inpainting the full image.
+    /// For slices without any known pixels within the crop, it extends two tiles around it.
+    /// When there are still no known pixels, [`Error::NoKnownPixels`] is returned.
+    ///
+    /// ### Arguments:
+    ///
+    /// * `shape`: rows and columns of the full image.
+    /// * `tile_size`: width and height of the tiles that are written.
+    /// * `options`: options to configure the algorithm.
+    /// * `read_mask`: returns the part of the mask in the requested region.
+    /// * `read_image`: returns the part of the image in the requested region.
+    /// * `write_image`: receives every inpainted tile of the image exactly once.
+    ///
+    /// Errors of the callbacks are returned as is, errors of inpainting are converted
+    /// into the error type of the callbacks.
+    pub fn telea_inpaint_tiled<ImageType, MaskType, E, ReadMask, ReadImage, WriteImage>(
+        &mut self,
+        shape: (usize, usize),
+        tile_size: usize,
+        options: &TeleaOptions,
+        mut read_mask: ReadMask,
+        mut read_image: ReadImage,
+        mut write_image: WriteImage,
+    ) -> core::result::Result<(), E>
+    where
+        ImageType: AsPrimitive<f32> + Copy,
+        f32: num_traits::AsPrimitive<ImageType>,
+        MaskType: AsPrimitive<f32> + Copy + 'static,
+        E: From<Error>,
+        ReadMask: FnMut(TileRegion) -> core::result::Result<Array2<MaskType>, E>,
+        ReadImage: FnMut(TileRegion) -> core::result::Result<Array3<ImageType>, E>,
+        WriteImage: FnMut(TileRegion, ArrayView3<ImageType>) -> core::result::Result<(), E>,
+    {
+        let resolution = USizeVec2::new(shape.1, shape.0);
+        let separation = region_separation(options);
+        let context = region_context(options);
+
+        let mut components = Vec::new();
+        for tile in tiles(resolution, tile_size) {
+            let mask = read_mask(tile.into())?;
+            check_shape(mask.shape(), tile)?;
+            components.extend(
+                find_regions(
+                    tile.max - tile.min,
+                    |y, x| is_masked(mask[[y, x]]),
+                    separation,
+                    &mut self.visited,
+                )
+                .into_iter()
+                .map(|region| Region::new(region.min + tile.min, region.max + tile.min)),
+            );
+        }
+        let regions = merge_regions(components, separation);
+        let conversion = OutputConversion::between::<f32, ImageType>();
+        let max_crop_size = 3 * tile_size.max(1);
+
+        let mut stored: Vec<Option<StoredRegion>> = regions.iter().map(|_| None).collect();
+        // Inpainted pixels of slices by row, column and region,
+        // which are known pixels for the slices right of and below them
+        let mut slices: BTreeMap<(usize, usize, usize), Vec<f32>> = BTreeMap::new();
+        for tile in tiles(resolution, tile_size) {
+            if tile.min.x == 0 {
+                slices = slices.split_off(&(tile.min.y.saturating_sub(context), 0, 0));
+            }
+            let mut image = read_image(tile.into())?;
+            check_shape(image.shape(), tile)?;
+            let channels = image.dim().2;
+
+            for (index, region) in regions
+                .iter()
+                .enumerate()
+                .filter(|(_, region)| region.intersects(&tile))
+            {
+                let part = region.intersection(&tile);
+                let crop = region.expand(context, resolution);
+                let crop_size = crop.max - crop.min;
+                if crop_size.x <= max_crop_size && crop_size.y <= max_crop_size {
+                    if stored[index].is_none() {
+                        self.read_crop(crop, channels, &mut read_mask, &mut read_image)?;
+                        let local = Region::new(USizeVec2::ZERO, crop_size);
+                        self.process(local, options, local, &mut |_| ControlFlow::Continue(()))?;
+                        stored[index] = Some(self.keep_region(*region, crop, channels));
+                    }
+                    if let Some(inpainted) = &stored[index] {
+                        inpainted.write(&mut image, tile.min, part, |value| {
+                            conversion.convert(value)
+                        });
+                    }
+                    if tile.contains(region.max - 1) {
+                        stored[index] = None;
+                    }
+                    continue;
+                }
+
+                let region_crop = crop;
+                let base_margin = context.max(tile_size);
+                let mut margin = base_margin;
+                let crop = loop {
+                    let crop = part.expand(margin, resolution).intersection(&region_crop);
+                    self.read_crop(crop, channels, &mut read_mask, &mut read_image)?;
+                    for y in crop.min.y..crop.max.y {
+                        for ((_, x, _), values) in slices
+                            .range((y, crop.min.x, 0)..(y, crop.max.x, 0))
+                            .filter(|((.., slice_region), _)| *slice_region == index)
+                        {
+                            self.set_known(crop, USizeVec2::new(*x, y), values);
+                        }
+                    }
+                    let local = Region::new(USizeVec2::ZERO, crop.max - crop.min);
+                    match self.process(local, options, local, &mut |_| ControlFlow::Continue(())) {
+                        // Slices deep inside a hole may not have known pixels within the margin,
+                        // which is doubled once to keep the crop bounded
+                        Err(Error::NoKnownPixels) if margin == base_margin => {
+                            margin *= 2;
+                        }
+                        result => {
+                            result?;
+                            break crop;
+                        }
+                    }
+                };
+
+                for y in part.min.y..part.max.y {
+                    for x in part.min.x..part.max.x {
+                        let position = USizeVec2::new(x, y);
+                        let Some(values) = self.inpainted_pixel(crop, position) else {
+                            continue;
+                        };
+                        let target = position - tile.min;
+                        for (value, inpainted) in image
+                            .slice_mut(s![target.y, target.x, ..])
+                            .iter_mut()
+                            .zip(values)
+                        {
+                            *value = conversion.convert(*inpainted);
+                        }
+                        if y + context >= tile.max.y || x + context >= tile.max.x {
+                            slices.insert((y, x, index), values.to_vec());
+                        }
+                    }
+                }
+            }
+
+            write_image(tile.into(), image.view())?;
+        }
+
+        Ok(())
+    }
+
+    /// Read the crop of the image and mask from the callbacks and load it for processing
+    fn read_crop<ImageType, MaskType, E, ReadMask, ReadImage>(
+        &mut self,
+        crop: Region,
+        channels: usize,
+        read_mask: &mut ReadMask,
+        read_image: &mut ReadImage,
+    ) -> core::result::Result<(), E>
+    where
+        ImageType: AsPrimitive<f32> + Copy,
+        MaskType: AsPrimitive<f32> + Copy + 'static,
+        E: From<Error>,
+        ReadMask: FnMut(TileRegion) -> core::result::Result<Array2<MaskType>, E>,
+        ReadImage: FnMut(TileRegion) -> core::result::Result<Array3<ImageType>, E>,
+    {
+        let crop_image = read_image(crop.into())?;
+        let crop_mask = read_mask(crop.into())?;
+        check_shape(crop_image.shape(), crop)?;
+        check_shape(crop_mask.shape(), crop)?;
+        if crop_image.dim().2 != channels {
+            return Err(Error::DimensionMismatch.into());
+        }
+
+        let (_, max_value) = value_range::<ImageType>();
+        let local = Region::new(USizeVec2::ZERO, crop.max - crop.min);
+        self.load_crop(&crop_image.view(), &crop_mask.view(), local, max_value);
+        Ok(())
+    }
+
+    /// Copy the inpainted pixels of the region out of the processed crop
+    fn keep_region(&self, region: Region, crop: Region, channels: usize) -> StoredRegion {
+        let size = region.max - region.min;
+        let mut values = Array3::zeros((size.y, size.x, channels));
+        let mut inpainted = Array2::from_elem((size.y, size.x), false);
+        for y in region.min.y..region.max.y {
+            for x in region.min.x..region.max.x {
+                let Some(pixel) = self.inpainted_pixel(crop, USizeVec2::new(x, y)) else {
+                    continue;
+                };
+                let local = USizeVec2::new(x, y) - region.min;
+                values
+                    .slice_mut(s![local.y, local.x, ..])
+                    .iter_mut()
+                    .zip(pixel)
+                    .for_each(|(value, pixel)| *value = *pixel);
+                inpainted[[local.y, local.x]] = true;
+            }
+        }
+        StoredRegion {
+            region,
+            values,
+            inpainted,
+        }
+    }
+}
+
+/// ## Inpaint an image in tiles, which are read from and written to callbacks.
+///
+/// See [`TeleaWorkspace::telea_inpaint_tiled`] for the details.
+///
+/// ### Example
+/// ```rust
+/// use inpaint::{Error, TeleaOptions, TileRegion, telea_inpaint_tiled};
+/// use ndarray::{Array2, Array3, s};
+///
+/// let image = Array3::<f32>::from_elem((300, 200, 3), 0.5);
+/// let mut mask = Array2::<f32>::zeros((300, 200));
+/// mask.slice_mut(s![100..120, 50..90]).fill(1.0);
+/// let mut output = Array3::<f32>::zeros((300, 200, 3));
+///
+/// let rows = |tile: TileRegion| tile.y..tile.y + tile.height;
+/// let columns = |tile: TileRegion| tile.x..tile.x + tile.width;
+/// telea_inpaint_tiled(
+///     (300, 200),
+///     64,
+///     &TeleaOptions::new(5),
+///     |tile| Ok::<_, Error>(mask.slice(s![rows(tile), columns(tile)]).to_owned()),
+///     |tile| Ok(image.slice(s![rows(tile), columns(tile), ..]).to_owned()),
+///     |tile, inpainted| {
+///         output
+///             .slice_mut(s![rows(tile), columns(tile), ..])
+///             .assign(&inpainted);
+///         Ok(())
+///     },
+/// )
+/// .unwrap();
+/// ```
+pub fn telea_inpaint_tiled<ImageType, MaskType, E, ReadMask, ReadImage, WriteImage>(
+    shape: (usize, usize),
+    tile_size: usize,
+    options: &TeleaOptions,
+    read_mask: ReadMask,
+    read_image: ReadImage,
+    write_image: WriteImage,
+) -> core::result::Result<(), E>
+where
+    ImageType: AsPrimitive<f32> + Copy,
+    f32: num_traits::AsPrimitive<ImageType>,
+    MaskType: AsPrimitive<f32> + Copy + 'static,
+    E: From<Error>,
+    ReadMask: FnMut(TileRegion) -> core::result::Result<Array2<MaskType>, E>,
+    ReadImage: FnMut(TileRegion) -> core::result::Result<Array3<ImageType>, E>,
+    WriteImage: FnMut(TileRegion, ArrayView3<ImageType>) -> core::result::Result<(), E>,
+{
+    TeleaWorkspace::new().telea_inpaint_tiled(
+        shape,
+        tile_size,
+        options,
+        read_mask,
+        read_image,
+        write_image,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telea::telea_inpaint_with_options;
+    use ndarray::{Array2, Array3, s};
+
+    /// Inpaint the image in tiles from memory, returning the result and the requested regions
+    fn try_inpaint_tiled(
+        image: &Array3<f32>,
+        mask: &Array2<f32>,
+        tile_size: usize,
+        options: &TeleaOptions,
+    ) -> (core::result::Result<Array3<f32>, Error>, Vec<TileRegion>) {
+        let mut output = Array3::from_elem(image.raw_dim(), f32::NAN);
+        let mut requested = Vec::new();
+        let mut read_image = |tile: TileRegion| {
+            requested.push(tile);
+            Ok::<_, Error>(
+                image
+                    .slice(s![
+                        tile.y..tile.y + tile.height,
+                        tile.x..tile.x + tile.width,
+                        ..
+                    ])
+                    .to_owned(),
+            )
+        };
+        let result = telea_inpaint_tiled(
+            mask.dim(),
+            tile_size,
+            options,
+            |tile| {
+                Ok(mask
+                    .slice(s![
+                        tile.y..tile.y + tile.height,
+                        tile.x..tile.x + tile.width
+                    ])
+                    .to_owned())
+            },
+            &mut read_image,
+            |tile, inpainted| {
+                output
+                    .slice_mut(s![
+                        tile.y..tile.y + tile.height,
+                        tile.x..tile.x + tile.width,
+                        ..
+                    ])
+                    .assign(&inpainted);
+                Ok(())
+            },
+        );
+        (result.map(|_| output), requested)
+    }
+
+    /// Inpaint the image in tiles from memory, returning the output and the requested regions
+    fn inpaint_tiled(
+        image: &Array3<f32>,
+        mask: &Array2<f32>,
+        tile_size: usize,
+        options: &TeleaOptions,
+    ) -> (Array3<f32>, Vec<TileRegion>) {
+        let (result, requested) = try_inpaint_tiled(image, mask, tile_size, options);
+        (result.unwrap(), requested)
+    }
+
+    /// Image with a gradient and masked holes of different sizes, some crossing tile borders
+    fn test_data() -> (Array3<f32>, Array2<f32>) {
+        let image = Array3::from_shape_fn((150, 170, 3), |(y, x, channel)| {
+            ((y * 3 + x * 7 + channel * 11) % 29) as f32 / 29.0
+        });
+        let mut mask = Array2::zeros((150, 170));
+        mask.slice_mut(s![20..70, 30..90]).fill(1.0);
+        mask.slice_mut(s![100..104, 0..170]).fill(1.0);
+        mask.slice_mut(s![130..150, 140..170]).fill(1.0);
+        mask[[10, 150]] = 1.0;
+        (image, mask)
+    }
+
+    /// Regions that fit in the crops are the same as in the full image,
+    /// regions that are inpainted in slices are close to it
+    #[test]
+    fn test_tiled_matches_full_image() {
+        let (image, mask) = test_data();
+        for options in [TeleaOptions::new(5), TeleaOptions::new(2)] {
+            let mut expected = image.clone();
+            telea_inpaint_with_options(&mut expected.view_mut(), &mask.view(), &options).unwrap();
+
+            let context = region_context(&options);
+            let resolution = USizeVec2::new(170, 150);
+            let mut visited = Vec::new();
+            let regions = find_regions(
+                resolution,
+                |y, x| is_masked(mask[[y, x]]),
+                region_separation(&options),
+                &mut visited,
+            );
+            for tile_size in [32, 45, 64, 500] {
+                let (output, _) = inpaint_tiled(&image, &mask, tile_size, &options);
+                let mut difference = 0.0f32;
+                let mut count = 0;
+                for region in &regions {
+                    let crop = region.expand(context, resolution);
+                    let sliced = (crop.max - crop.min).max_element() > 3 * tile_size;
+                    let area = s![region.min.y..region.max.y, region.min.x..region.max.x, ..];
+                    if !sliced {
+                        assert_eq!(
+                            output.slice(area),
+                            expected.slice(area),
+                            "tile size {tile_size}"
+                        );
+                        continue;
+                    }
+                    for (output, expected) in output.slice(area).iter().zip(expected.slice(area)) {
+                        difference += (output - expected).abs();
+                        count += 1;
+                    }
+                }
+                assert!(
+                    difference / count.max(1) as f32 <= 0.03,
+                    "tile size {tile_size}"
+                );
+                let known = mask.mapv(|value| value == 0.0);
+                for ((y, x, channel), value) in output.indexed_iter() {
+                    if known[[y, x]] {
+                        assert_eq!(*value, image[[y, x, channel]]);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_tiled_requests_bounded_regions() {
+        let image = Array3::from_elem((200, 200, 1), 0.5);
+        let mut mask = Array2::zeros((200, 200));
+        mask.slice_mut(s![49..51, 49..51]).fill(1.0);
+        mask[[150, 120]] = 1.0;
+        let options = TeleaOptions::new(3);
+
+        let (_, requested) = inpaint_tiled(&image, &mask, 50, &options);
+        // Every tile and the crops of both regions, even though the first one overlaps 4 tiles
+        assert_eq!(requested.len(), 16 + 2);
+        let context = region_context(&options);
+        for tile in requested {
+            assert!(tile.width <= 50.max(2 * context + 1));
+            assert!(tile.height <= 50.max(2 * context + 1));
+        }
+    }
+
+    /// Largest difference between neighboring pixels, which shows seams between the tiles
+    fn largest_step(image: &Array3<f32>) -> f32 {
+        let rows = &image.slice(s![1.., .., ..]) - &image.slice(s![..-1, .., ..]);
+        let columns = &image.slice(s![.., 1.., ..]) - &image.slice(s![.., ..-1, ..]);
+        rows.iter()
+            .chain(columns.iter())
+            .fold(0.0, |largest, step| step.abs().max(largest))
+    }
+
+    #[test]
+    fn test_tiled_slices_large_holes() {
+        let image = Array3::from_shape_fn((200, 200, 1), |(y, x, _)| {
+            0.2 + 0.5 * x as f32 / 200.0 + 0.2 * y as f32 / 200.0
+        });
+        let mut mask = Array2::zeros((200, 200));
+        mask.slice_mut(s![30..170, 40..160]).fill(1.0);
+        mask.slice_mut(s![185..188, 0..200]).fill(1.0);
+        let options = TeleaOptions::new(3);
+        let mut expected = image.clone();
+        telea_inpaint_with_options(&mut expected.view_mut(), &mask.view(), &options).unwrap();
+
+        let (output, requested) = inpaint_tiled(&image, &mask, 50, &options);
+        let context = region_context(&options);
+        for tile in requested {
+            assert!(tile.width <= 150.max(50 + 2 * context), "{tile:?}");
+            assert!(tile.height <= 150.max(50 + 2 * context), "{tile:?}");
+        }
+
+        let masked = mask.iter().filter(|value| **value != 0.0).count();
+        let difference = (&output - &expected).mapv(f32::abs).sum() / masked as f32;
+        assert!(difference < 0.05, "{difference}");
+        assert!(largest_step(&output) <= largest_step(&expected));
+    }
+
+    #[test]
+    /// Slices far from the known pixels only grow their crop up to two tiles around them
+    fn test_tiled_bounds_slices_without_known_pixels() {
+        let image = Array3::from_shape_fn((300, 300, 1), |(y, x, _)| (x + y) as f32 / 600.0);
+        let options = TeleaOptions::new(3);
+        let largest = |requested: &[TileRegion]| {
+            requested
+                .iter()
+                .map(|tile| tile.width.max(tile.height))
+                .max()
+                .unwrap()
+        };
+
+        // Only known at the edges of the hole, which the slices reach through their neighbors
+        let mut mask = Array2::zeros((300, 300));
+        mask.slice_mut(s![10..290, 10..290]).fill(1.0);
+        let (result, requested) = try_inpaint_tiled(&image, &mask, 32, &options);
+        assert!(result.unwrap().iter().all(|value| value.is_finite()));
+        assert!(largest(&requested) <= 5 * 32);
+
+        // The first slice in the corner has no known pixels within two tiles
+        mask.slice_mut(s![0..290, 0..290]).fill(1.0);
+        let (result, requested) = try_inpaint_tiled(&image, &mask, 32, &options);
+        assert!(matches!(result, Err(Error::NoKnownPixels)));
+        assert!(largest(&requested) <= 5 * 32);
+    }
+
+    #[test]
+    fn test_tiled_callback_errors() {
+        let image = Array3::<f32>::zeros((20, 20, 1));
+        let result = telea_inpaint_tiled(
+            (20, 20),
+            8,
+            &TeleaOptions::default(),
+            |_| Ok(Array2::<f32>::zeros((3, 3))),
+            |_| Ok(image.clone()),
+            |_, _| Ok(()),
+        );
+        assert!(matches!(result, Err(Error::DimensionMismatch)));
+
+        let result: core::result::Result<(), Error> = telea_inpaint_tiled(
+            (20, 20),
+            8,
+            &TeleaOptions::default(),
+            |_| Err::<Array2<f32>, _>(Error::NoData),
+            |_| Ok(image.clone()),
+            |_, _| Ok(()),
+        );
+        assert!(matches!(result, Err(Error::NoData)));
+    }
+}
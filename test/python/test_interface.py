@@ -4,7 +4,10 @@ import inpaint
 import numpy as np
 
 
-@pytest.mark.parametrize(("input_type"), [np.float32, np.uint32, np.int32])
+@pytest.mark.parametrize(
+    ("input_type"),
+    [np.uint8, np.uint16, np.float32, np.float64, np.uint32, np.int32, np.int16],
+)
 def test_numpy_interface(input_type) -> None:
     """Make sure return type is correct, as telea inpaint supports both images and arrays
 
@@ -12,9 +15,291 @@ def test_numpy_interface(input_type) -> None:
         input_type:: type for array, should match return value
     """
     test_image = Image.open("./test/images/input/bird.png")
-    test_array = np.asarray(test_image, dtype=np.float32) / 256.0
     test_array = np.asarray(test_image, dtype=input_type)
 
     output = inpaint.telea(test_array, test_array[:, :, 0])
 
     assert type(output) is type(test_array)
+    assert output.dtype == input_type
+
+
+@pytest.mark.parametrize(("mask_type"), [np.bool_, np.uint8, np.float32, np.int64])
+def test_mask_types(mask_type) -> None:
+    """The mask can have another dtype than the image"""
+    test_image = Image.open("./test/images/input/bird.png")
+    test_array = np.asarray(test_image, dtype=np.uint8)
+    mask = np.asarray(Image.open("./test/images/mask/thin.png"))[:, :, 0] > 0
+
+    expected = inpaint.telea(test_array, mask.astype(np.uint8))
+    output = inpaint.telea(test_array, mask.astype(mask_type))
+
+    np.testing.assert_array_equal(output, expected)
+
+
+@pytest.mark.parametrize(("input_type"), [np.float16, np.float32, np.float64])
+def test_float_types(input_type) -> None:
+    """Float arrays should be inpainted and returned in their own precision"""
+    test_array = np.zeros((64, 64, 3), dtype=input_type)
+    test_array[:, 32:] = 1.0
+    mask = np.zeros((64, 64), dtype=input_type)
+    mask[10:40, 20:50] = 1.0
+
+    output = inpaint.telea(test_array, mask)
+
+    assert output.dtype == input_type
+    assert np.all((output >= 0.0) & (output <= 1.0))
+
+
+def test_progress_callback() -> None:
+    """Progress should be reported up to the total amount of masked pixels"""
+    test_array = np.zeros((64, 64, 3), dtype=np.float32)
+    mask = np.zeros((64, 64), dtype=np.float32)
+    mask[10:40, 20:50] = 1.0
+    reports = []
+
+    inpaint.telea(test_array, mask, progress=lambda filled, total: reports.append((filled, total)))
+
+    assert reports[-1] == (900, 900)
+
+
+def test_progress_cancel() -> None:
+    """Returning False from the progress callback cancels inpainting"""
+    test_array = np.zeros((64, 64, 3), dtype=np.float32)
+    mask = np.zeros((64, 64), dtype=np.float32)
+    mask[10:40, 20:50] = 1.0
+
+    with pytest.raises(inpaint.InpaintError):
+        inpaint.telea(test_array, mask, progress=lambda filled, total: False)
+
+
+def test_inplace() -> None:
+    """Inpainting in place changes the array itself, with the same result as a copy"""
+    test_array = np.array(Image.open("./test/images/input/bird.png"), dtype=np.uint8)
+    mask = np.asarray(Image.open("./test/images/mask/thin.png"))[:, :, 0]
+
+    expected = inpaint.telea(test_array, mask)
+    output = inpaint.telea(test_array, mask, inplace=True)
+
+    assert output is test_array
+    np.testing.assert_array_equal(test_array, expected)
+
+    with pytest.raises(ValueError):
+        inpaint.telea(test_array.astype(np.int16), mask, inplace=True)
+
+
+def test_threads() -> None:
+    """Images can be inpainted from multiple threads at once"""
+    from concurrent.futures import ThreadPoolExecutor
+
+    test_array = np.asarray(Image.open("./test/images/input/bird.png"), dtype=np.float32)
+    mask = np.asarray(Image.open("./test/images/mask/thin.png"))[:, :, 0]
+    expected = inpaint.telea(test_array, mask)
+
+    with ThreadPoolExecutor(max_workers=4) as executor:
+        outputs = list(executor.map(lambda _: inpaint.telea(test_array, mask), range(4)))
+
+    for output in outputs:
+        np.testing.assert_array_equal(output, expected)
+
+
+@pytest.mark.parametrize(("mode"), ["1", "L", "LA", "RGB", "RGBA", "I;16", "I", "F"])
+def test_pil_modes(mode) -> None:
+    """PIL images are returned in their own mode, with masks of any mode"""
+    test_image = Image.open("./test/images/input/bird.png").convert(mode)
+    mask = Image.open("./test/images/mask/thin.png").convert("L")
+
+    output = inpaint.telea(test_image, mask)
+
+    assert output.mode == test_image.mode
+    assert output.size == test_image.size
+
+
+def test_grayscale() -> None:
+    """2 dimensional images are inpainted like a single channel"""
+    test_array = np.asarray(Image.open("./test/images/input/bird.png"), dtype=np.uint8)
+    mask = np.asarray(Image.open("./test/images/mask/thin.png"))
+
+    expected = inpaint.telea(test_array[:, :, :1], mask)
+    output = inpaint.telea(test_array[:, :, 0], mask)
+
+    assert output.shape == test_array.shape[:2]
+    np.testing.assert_array_equal(output, expected[:, :, 0])
+
+
+def test_non_contiguous() -> None:
+    """Strided views are inpainted like contiguous copies"""
+    test_array = np.asarray(Image.open("./test/images/input/bird.png"), dtype=np.float32)
+    mask = np.asarray(Image.open("./test/images/mask/thin.png"))[:, :, 0]
+    view = test_array[::-1, ::2, ::-1]
+    mask_view = mask[::-1, ::2]
+    assert not view.flags.c_contiguous
+
+    output = inpaint.telea(view, mask_view)
+
+    np.testing.assert_array_equal(
+        output,
+        inpaint.telea(np.ascontiguousarray(view), np.ascontiguousarray(mask_view)),
+    )
+
+
+@pytest.mark.parametrize(
+    ("image", "mask"),
+    [
+        (np.zeros((8, 8, 3, 2), dtype=np.float32), np.zeros((8, 8))),
+        (np.zeros((8, 8, 3), dtype=np.float32), np.zeros((8, 4))),
+        (np.zeros((8, 8, 3), dtype=np.complex64), np.zeros((8, 8))),
+        (np.zeros((8, 8, 3), dtype=np.float32), np.zeros(8)),
+        (np.zeros((8, 8, 3), dtype=np.str_), np.zeros((8, 8))),
+    ],
+)
+def test_unsupported_inputs(image, mask) -> None:
+    """Unsupported inputs raise an InpaintError"""
+    with pytest.raises(inpaint.InpaintError):
+        inpaint.telea(image, mask)
+
+
+def test_error_is_runtime_error() -> None:
+    """Handlers for RuntimeError keep catching an InpaintError"""
+    assert issubclass(inpaint.InpaintError, RuntimeError)
+    with pytest.raises(RuntimeError):
+        inpaint.telea(np.zeros((8, 8, 3), dtype=np.float32), np.zeros(8))
+
+
+@pytest.mark.parametrize(("input_type"), [np.uint8, np.float32, np.int16])
+def test_batch(input_type) -> None:
+    """Every image of the stack is inpainted like a single image"""
+    test_array = np.asarray(Image.open("./test/images/input/bird.png"), dtype=input_type)
+    mask = np.asarray(Image.open("./test/images/mask/thin.png"))[:, :, 0]
+    images = np.stack([test_array, test_array[::-1], test_array[:, ::-1]])
+    masks = np.stack([mask, mask[::-1], mask[:, ::-1]])
+
+    output = inpaint.telea_batch(images, masks)
+
+    assert output.shape == images.shape
+    assert output.dtype == input_type
+    for image, image_mask, result in zip(images, masks, output):
+        np.testing.assert_array_equal(result, inpaint.telea(image, image_mask))
+
+
+def test_batch_more_images_than_threads() -> None:
+    """Batches with more images than threads, each with several holes, don't deadlock"""
+    import os
+
+    test_array = np.zeros((32, 48, 3), dtype=np.float32)
+    test_array[:, 24:] = 1.0
+    mask = np.zeros((32, 48), dtype=np.uint8)
+    mask[8:16, 4:12] = 255
+    mask[8:16, 34:42] = 255
+    count = 4 * (os.cpu_count() or 1) + 1
+
+    output = inpaint.telea_batch(np.stack([test_array] * count), mask)
+
+    expected = inpaint.telea(test_array, mask)
+    for result in output:
+        np.testing.assert_array_equal(result, expected)
+
+
+def test_batch_lists() -> None:
+    """Lists of grayscale images can be inpainted with a single shared mask"""
+    test_array = np.asarray(Image.open("./test/images/input/bird.png"), dtype=np.uint8)
+    mask = np.asarray(Image.open("./test/images/mask/thin.png"))[:, :, 0]
+    images = [test_array[:, :, channel] for channel in range(3)]
+
+    output = inpaint.telea_batch(images, mask)
+
+    assert output.shape == (3,) + mask.shape
+    for image, result in zip(images, output):
+        np.testing.assert_array_equal(result, inpaint.telea(image, mask))
+
+    with pytest.raises(inpaint.InpaintError):
+        inpaint.telea_batch(images, [mask, mask])
+
+
+def test_cv2_compat() -> None:
+    """cv2.inpaint arguments inpaint like telea, with non-zero masks and rounded radius"""
+    from inpaint import cv2_compat
+
+    test_array = np.asarray(Image.open("./test/images/input/bird.png"), dtype=np.uint8)
+    mask = np.asarray(Image.open("./test/images/mask/thin.png"))[:, :, :1]
+    expected = inpaint.telea(test_array[:, :, ::-1], mask[:, :, 0] > 0, 3)
+
+    output = cv2_compat.inpaint(test_array[:, :, ::-1], mask // 255, 2.6, cv2_compat.INPAINT_TELEA)
+    np.testing.assert_array_equal(output, expected)
+
+    dst = np.empty_like(test_array)
+    result = cv2_compat.inpaint(test_array[:, :, ::-1], mask, 3, cv2_compat.INPAINT_TELEA, dst)
+    assert result is dst
+    np.testing.assert_array_equal(dst, expected)
+
+    with pytest.raises(NotImplementedError):
+        cv2_compat.inpaint(test_array, mask, 3, cv2_compat.INPAINT_NS)
+
+
+@pytest.mark.parametrize(
+    ("src", "mask"),
+    [
+        (np.zeros((8, 8, 4), dtype=np.uint8), np.zeros((8, 8), dtype=np.uint8)),
+        (np.zeros((8, 8, 3), dtype=np.float32), np.zeros((8, 8), dtype=np.uint8)),
+        (np.zeros((8, 8, 3), dtype=np.uint16), np.zeros((8, 8), dtype=np.uint8)),
+        (np.zeros((8, 8), dtype=np.float64), np.zeros((8, 8), dtype=np.uint8)),
+        (np.zeros((8, 8), dtype=np.int32), np.zeros((8, 8), dtype=np.uint8)),
+        (np.zeros((8, 8), dtype=np.uint8), np.zeros((8, 8), dtype=np.bool_)),
+        (np.zeros((8, 8), dtype=np.uint8), np.zeros((8, 8), dtype=np.float32)),
+        (np.zeros((8, 8), dtype=np.uint8), np.zeros((8, 8, 3), dtype=np.uint8)),
+        (np.zeros((8, 8), dtype=np.uint8), np.zeros((8, 4), dtype=np.uint8)),
+    ],
+)
+def test_cv2_compat_unsupported(src, mask) -> None:
+    """Images and masks that cv2.inpaint rejects are rejected as well"""
+    from inpaint import cv2_compat
+
+    with pytest.raises(inpaint.InpaintError):
+        cv2_compat.inpaint(src, mask, 3, cv2_compat.INPAINT_TELEA)
+
+
+def test_torch_tensors() -> None:
+    """CPU torch tensors in CHW layout are inpainted and returned as tensors"""
+    torch = pytest.importorskip("torch")
+    test_array = np.asarray(Image.open("./test/images/input/bird.png"), dtype=np.float32)
+    mask = np.asarray(Image.open("./test/images/mask/thin.png"))[:, :, 0]
+    expected = inpaint.telea(test_array, mask)
+
+    tensor = torch.from_numpy(np.ascontiguousarray(test_array.transpose(2, 0, 1)))
+    output = inpaint.telea(tensor, torch.from_numpy(mask > 0), layout="CHW")
+
+    assert isinstance(output, torch.Tensor)
+    assert output.shape == tensor.shape
+    np.testing.assert_array_equal(output.numpy().transpose(1, 2, 0), expected)
+
+    batch = inpaint.telea_batch(tensor[None], torch.from_numpy(mask[None]), layout="CHW")
+    assert isinstance(batch, torch.Tensor)
+    np.testing.assert_array_equal(batch[0].numpy(), output.numpy())
+
+    result = inpaint.telea(tensor, torch.from_numpy(mask), inplace=True, layout="CHW")
+    assert result is tensor
+    np.testing.assert_array_equal(tensor.numpy(), output.numpy())
+
+
+def test_dlpack() -> None:
+    """Any DLPack object is inpainted without copying it"""
+
+    class Tensor:
+        def __init__(self, array):
+            self.array = array
+
+        def __dlpack__(self, **kwargs):
+            return self.array.__dlpack__(**kwargs)
+
+        def __dlpack_device__(self):
+            return self.array.__dlpack_device__()
+
+    test_array = np.asarray(Image.open("./test/images/input/bird.png"), dtype=np.uint8)
+    mask = np.asarray(Image.open("./test/images/mask/thin.png"))[:, :, 0]
+    expected = inpaint.telea(test_array, mask)
+
+    channels_first = np.array(test_array.transpose(2, 0, 1))
+    inpaint.telea(Tensor(channels_first), Tensor(mask), inplace=True, layout="CHW")
+
+    np.testing.assert_array_equal(channels_first.transpose(1, 2, 0), expected)
+    with pytest.raises(ValueError):
+        inpaint.telea(test_array, mask, layout="WHC")